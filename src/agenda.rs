@@ -0,0 +1,60 @@
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+/// Date/time layouts accepted at the start of each line of the agenda command's output.
+const START_FORMATS: &[&str] = &["%Y-%m-%d %H:%M", "%d.%m.%Y %H:%M", "%m/%d/%Y %H:%M"];
+
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub start: DateTime<Local>,
+    pub title: String,
+}
+
+/// Parses lines of the form `<date> <time> <title>`; lines without a recognisable start time
+/// (all-day events, day headers) are skipped.
+pub fn parse_events(output: &str) -> Vec<Event> {
+    let mut events: Vec<Event> = output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.trim().splitn(3, ' ');
+            let stamp = format!("{} {}", fields.next()?, fields.next()?);
+            let title = fields.next()?.trim().to_string();
+            let naive = START_FORMATS.iter().find_map(|f| NaiveDateTime::parse_from_str(&stamp, f).ok())?;
+            let start = Local.from_local_datetime(&naive).earliest()?;
+            Some(Event { start, title })
+        })
+        .collect();
+    events.sort_by_key(|e| e.start);
+    events
+}
+
+/// Renders the next event relative to `now`: `now: <title>` within `lead_minutes` either side of
+/// its start, `next: <title> in 12m` otherwise, and nothing if it is beyond `horizon_hours`.
+pub fn render(events: &[Event], now: DateTime<Local>, lead_minutes: i64, horizon_hours: i64) -> String {
+    let Some(event) = events.iter().find(|e| (e.start - now).num_minutes() >= -lead_minutes) else {
+        return String::new();
+    };
+    let minutes = (event.start - now).num_minutes();
+    if minutes <= lead_minutes {
+        format!("now: {}", event.title)
+    } else if minutes <= horizon_hours * 60 {
+        format!("next: {} in {}", event.title, format_minutes(minutes))
+    } else {
+        String::new()
+    }
+}
+
+fn format_minutes(minutes: i64) -> String {
+    if minutes < 60 {
+        format!("{}m", minutes)
+    } else {
+        format!("{}h{:02}m", minutes / 60, minutes % 60)
+    }
+}
+
+pub async fn fetch(command: &str) -> Result<Vec<Event>> {
+    let output = crate::command::run_command_clean("sh", &["-c", command]).await?;
+    let events = parse_events(&output);
+    tracing::debug!(target: "monitor::agenda", count = events.len(), "Fetched agenda");
+    Ok(events)
+}
@@ -0,0 +1,214 @@
+use crate::budget::Budget;
+use crate::command::CommandError;
+use crate::config::Config;
+use crate::health::Outcome;
+use crate::pango::Pango;
+use crate::registry::{ModuleId, Section};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+/// What a monitor run produced, sent from the monitor tasks to the main loop.
+#[derive(Debug, Clone)]
+pub struct Update {
+    pub id: ModuleId,
+    pub outcome: Outcome,
+    /// When the monitor produced the value, so updates that sat in the queue too long can be dropped.
+    pub created: Instant,
+}
+
+impl Update {
+    pub fn new(id: ModuleId, outcome: Outcome) -> Self {
+        Update { id, outcome, created: Instant::now() }
+    }
+
+    pub fn success(id: ModuleId, value: String) -> Self {
+        Update::new(id, Outcome::Success(value))
+    }
+
+    pub fn failure(id: ModuleId, error: &anyhow::Error) -> Self {
+        let command = error.downcast_ref::<CommandError>().cloned();
+        Update::new(id, Outcome::Failure { message: error.to_string(), command })
+    }
+}
+
+/// How the bar decorates each module's text.
+pub enum Markup<'a> {
+    Plain,
+    /// dwm status2d color escapes.
+    Status2d(&'a HashMap<ModuleId, String>),
+    /// Escaped text in `<span>` tags with the module's color and font.
+    Pango(&'a Pango, &'a HashMap<ModuleId, String>),
+}
+
+/// What goes between two neighbouring modules, depending on where they sit.
+#[derive(Debug, Clone)]
+pub struct Separators {
+    /// Between the pinned sections and the rest.
+    section: String,
+    group: String,
+    module: String,
+    /// The index of the configured group each grouped module belongs to.
+    groups: HashMap<ModuleId, usize>,
+}
+
+impl Separators {
+    pub fn new(config: &Config) -> Self {
+        let groups = config
+            .groups
+            .iter()
+            .enumerate()
+            .flat_map(|(index, ids)| ids.iter().map(move |id| (ModuleId::from(id.as_str()), index)))
+            .collect();
+        Separators {
+            section: config.section_separator.clone().unwrap_or_else(|| config.group_separator.clone()),
+            group: config.group_separator.clone(),
+            module: config.module_separator.clone(),
+            groups,
+        }
+    }
+
+    fn between(&self, (previous, previous_section): (&ModuleId, Section), (id, section): (&ModuleId, Section)) -> &str {
+        if previous_section != section {
+            return &self.section;
+        }
+        match (self.groups.get(previous), self.groups.get(id)) {
+            (Some(a), Some(b)) if a == b => &self.module,
+            _ => &self.group,
+        }
+    }
+}
+
+/// Everything one line of the bar is built from. Rendering is a pure function of these, so
+/// sinks only decide what goes in.
+pub struct Bar<'a> {
+    /// The modules to show, sorted by section.
+    pub order: &'a [(ModuleId, Section)],
+    pub results: &'a HashMap<ModuleId, String>,
+    pub markup: Markup<'a>,
+    pub budget: Option<&'a Budget>,
+    /// Shown before all modules.
+    pub overlay: Option<&'a str>,
+    pub separators: &'a Separators,
+}
+
+impl Bar<'_> {
+    /// Joins the modules with text, padded with a space at both ends.
+    pub fn assemble(&self) -> String {
+        let order = self.order;
+        // The overlay isn't a registered module, so the budget gives it the highest priority.
+        let mut parts: Vec<(ModuleId, String)> = self
+            .overlay
+            .map(|text| (ModuleId::from(crate::overlay::TRIGGER), text.to_string()))
+            .into_iter()
+            .chain(order.iter().filter_map(|(id, _)| Some((id.clone(), self.results.get(id).filter(|s| !s.is_empty())?.clone()))))
+            .collect();
+        // The overlay sits at the very front, so it counts as part of the left section.
+        let section_of = |id: &ModuleId| order.iter().find(|(m, _)| m == id).map_or(Section::Left, |&(_, section)| section);
+        if let Some(budget) = self.budget {
+            // The two padding spaces count too; color escapes are invisible and don't. Cuts fall on
+            // character boundaries, so multi-byte text and emoji are never split into invalid UTF-8.
+            let separator_width = |a: &ModuleId, b: &ModuleId| self.separators.between((a, section_of(a)), (b, section_of(b))).chars().count();
+            parts = budget.fit(parts, 2, &separator_width);
+        }
+        let mut bar = String::from(" ");
+        let mut previous: Option<(ModuleId, Section)> = None;
+        for (id, text) in parts {
+            let section = section_of(&id);
+            if let Some((previous, previous_section)) = &previous {
+                bar.push_str(self.separators.between((previous, *previous_section), (&id, section)));
+            }
+            previous = Some((id.clone(), section));
+            match self.markup {
+                Markup::Plain => bar.push_str(&text),
+                Markup::Status2d(colors) => match colors.get(&id) {
+                    Some(color) => bar.push_str(&format!("^c{}^{}^d^", color, text)),
+                    None => bar.push_str(&text),
+                },
+                Markup::Pango(pango, colors) => bar.push_str(&pango.markup(&id, &text, colors.get(&id).map(String::as_str))),
+            }
+        }
+        bar.push(' ');
+        bar
+    }
+}
+
+/// Every module's text as a JSON object sorted by ID, e.g.
+/// `{"battery":"bat: 80% D","cpu_load":"cpu: 45%"}`. Hidden modules have an empty string.
+pub fn assemble_json(results: &HashMap<ModuleId, String>) -> String {
+    let sorted: BTreeMap<&str, &str> = results.iter().map(|(id, text)| (&**id, text.as_str())).collect();
+    serde_json::to_string(&sorted).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn separators() -> Separators {
+        Separators::new(&Config {
+            groups: vec![vec!["cpu_load".to_string(), "ram".to_string()]],
+            group_separator: " | ".to_string(),
+            module_separator: " · ".to_string(),
+            section_separator: Some(" ║ ".to_string()),
+            ..Config::default()
+        })
+    }
+
+    fn order(ids: &[(&str, Section)]) -> Vec<(ModuleId, Section)> {
+        ids.iter().map(|&(id, section)| (ModuleId::from(id), section)).collect()
+    }
+
+    fn texts(texts: &[(&str, &str)]) -> HashMap<ModuleId, String> {
+        texts.iter().map(|&(id, text)| (ModuleId::from(id), text.to_string())).collect()
+    }
+
+    fn assemble(order: &[(ModuleId, Section)], results: &HashMap<ModuleId, String>, overlay: Option<&str>) -> String {
+        let separators = separators();
+        Bar { order, results, markup: Markup::Plain, budget: None, overlay, separators: &separators }.assemble()
+    }
+
+    #[test]
+    fn separators_between_and_within_groups() {
+        let order = order(&[("cpu_load", Section::Center), ("ram", Section::Center), ("datetime", Section::Center)]);
+        let results = texts(&[("cpu_load", "cpu: 5%"), ("ram", "ram: 40%"), ("datetime", "12:00")]);
+        assert_eq!(assemble(&order, &results, None), " cpu: 5% · ram: 40% | 12:00 ");
+    }
+
+    #[test]
+    fn section_separator_between_pinned_sections() {
+        let order = order(&[("workspace", Section::Left), ("cpu_load", Section::Center), ("datetime", Section::Right)]);
+        let results = texts(&[("workspace", "1"), ("cpu_load", "cpu: 5%"), ("datetime", "12:00")]);
+        assert_eq!(assemble(&order, &results, None), " 1 ║ cpu: 5% ║ 12:00 ");
+    }
+
+    #[test]
+    fn empty_and_missing_modules_are_skipped() {
+        let order = order(&[("cpu_load", Section::Center), ("ram", Section::Center), ("vpn", Section::Center), ("datetime", Section::Center)]);
+        let results = texts(&[("cpu_load", "cpu: 5%"), ("vpn", ""), ("datetime", "12:00")]);
+        assert_eq!(assemble(&order, &results, None), " cpu: 5% | 12:00 ");
+        assert_eq!(assemble(&order, &HashMap::new(), None), "  ");
+    }
+
+    #[test]
+    fn overlay_goes_first() {
+        let order = order(&[("datetime", Section::Center)]);
+        let results = texts(&[("datetime", "12:00")]);
+        assert_eq!(assemble(&order, &results, Some("vol 40%")), " vol 40% ║ 12:00 ");
+    }
+
+    #[test]
+    fn status2d_colors_wrap_the_text() {
+        let order = order(&[("cpu_load", Section::Center), ("datetime", Section::Center)]);
+        let results = texts(&[("cpu_load", "cpu: 95%"), ("datetime", "12:00")]);
+        let colors = texts(&[("cpu_load", "#ff0000")]);
+        let separators = separators();
+        let bar = Bar { order: &order, results: &results, markup: Markup::Status2d(&colors), budget: None, overlay: None, separators: &separators };
+        assert_eq!(bar.assemble(), " ^c#ff0000^cpu: 95%^d^ | 12:00 ");
+    }
+
+    #[test]
+    fn json_is_sorted_and_escaped() {
+        let results = texts(&[("weather", "18°C \"sunny\""), ("agenda", "a\\b\nc"), ("vpn", "")]);
+        assert_eq!(assemble_json(&results), r#"{"agenda":"a\\b\nc","vpn":"","weather":"18°C \"sunny\""}"#);
+        assert_eq!(assemble_json(&HashMap::new()), "{}");
+    }
+}
@@ -0,0 +1,46 @@
+use crate::registry::ModuleId;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fmt;
+
+/// The text of every module as last applied by the bar writer, shared with the metrics endpoint
+/// and the trigger socket's queries.
+#[derive(Debug, Default, Serialize)]
+pub struct BarState {
+    pub modules: HashMap<ModuleId, String>,
+}
+
+/// A JSON query on the trigger socket, e.g. `{"cmd":"get","module":"all"}`.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase", deny_unknown_fields)]
+enum Query {
+    Get { module: String },
+}
+
+impl BarState {
+    /// Answers a JSON query with one line of JSON: `{"modules":{...}}` for `all`,
+    /// `{"module":..,"text":..}` for a single module, or `{"error":..}`.
+    pub fn answer(&self, query: &str) -> String {
+        match serde_json::from_str::<Query>(query) {
+            Ok(Query::Get { module }) if module == "all" => serde_json::to_string(self).unwrap_or_default(),
+            Ok(Query::Get { module }) => match self.modules.get(module.as_str()) {
+                Some(text) => json!({ "module": module, "text": text }).to_string(),
+                None => json!({ "error": format!("no value for module '{}'", module) }).to_string(),
+            },
+            Err(e) => json!({ "error": format!("invalid query: {}", e) }).to_string(),
+        }
+    }
+}
+
+/// One `module  text` line per module, sorted by ID; hidden modules show as `(hidden)`.
+impl fmt::Display for BarState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut modules: Vec<_> = self.modules.iter().collect();
+        modules.sort();
+        for (id, text) in modules {
+            writeln!(f, "{:<14} {}", id, if text.is_empty() { "(hidden)" } else { text })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use crate::bar::{Bar, Markup, Separators};
+use crate::budget::Budget;
+use crate::config::Config;
+use crate::registry::{ModuleId, ModuleRegistry, Section};
+use anyhow::Result;
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 10_000;
+
+/// Renders the bar from `results` `ITERATIONS` times, with status2d colors as for dwm, then writes
+/// it to the root window as often when an X server is available, and prints both timings.
+pub fn run(config: &Config, registry: &ModuleRegistry, results: &HashMap<ModuleId, String>, colors: &HashMap<ModuleId, String>) -> Result<()> {
+    let mut order: Vec<(ModuleId, Section)> = registry.order().iter().map(|id| (id.clone(), registry.section(id))).collect();
+    order.sort_by_key(|&(_, section)| section);
+    let budget = Budget::new(config, registry);
+    let separators = Separators::new(config);
+    let bar = Bar { order: &order, results, markup: Markup::Status2d(colors), budget: budget.as_ref(), overlay: None, separators: &separators };
+    let text = bar.assemble();
+    println!("{} of {} modules reported, {} characters: {}", results.len(), order.len(), text.chars().count(), text);
+
+    let started = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(black_box(&bar).assemble());
+    }
+    report("assemble", started.elapsed());
+
+    #[cfg(feature = "x11")]
+    match crate::xroot::XRoot::connect() {
+        Ok(xroot) => {
+            let started = Instant::now();
+            for _ in 0..ITERATIONS {
+                xroot.set_name(&text)?;
+            }
+            report("set_name", started.elapsed());
+        }
+        Err(e) => println!("{:<10} skipped: {:#}", "set_name", e),
+    }
+    Ok(())
+}
+
+fn report(name: &str, elapsed: Duration) {
+    let per_update = elapsed / ITERATIONS;
+    let per_second = f64::from(ITERATIONS) / elapsed.as_secs_f64();
+    println!("{:<10} {} iterations in {:?}: {:.0}/s, {:?} per update", name, ITERATIONS, elapsed, per_second, per_update);
+}
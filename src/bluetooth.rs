@@ -0,0 +1,199 @@
+use crate::config::BluetoothConfig;
+use crate::registry::ModuleId;
+use crate::util::percent_to_bar;
+use anyhow::Result;
+use futures_util::StreamExt;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+use zbus::fdo::ObjectManagerProxy;
+use zbus::zvariant::OwnedValue;
+use zbus::{message, Connection, MatchRule, MessageStream};
+
+const DEVICE_IFACE: &str = "org.bluez.Device1";
+const BATTERY_IFACE: &str = "org.bluez.Battery1";
+const BATTERY_BAR_WIDTH: u8 = 5;
+
+/// A connected Bluetooth device as reported by BlueZ.
+#[derive(Debug, Clone)]
+pub struct Device {
+    pub name: String,
+    pub icon: String,
+    pub battery: Option<u8>,
+}
+
+/// Talks to BlueZ over the system bus. The connection is kept open between polls.
+pub struct Bluetooth {
+    conn: Connection,
+    /// Set while BlueZ isn't running, so that is only logged when it changes.
+    service_down: AtomicBool,
+    /// Devices already notified about a low battery. A device leaves once it is seen charged
+    /// above the threshold again, not when it disconnects, so flaky reconnects don't notify twice.
+    low_battery: Mutex<HashSet<String>>,
+}
+
+impl Bluetooth {
+    pub async fn connect() -> Result<Self> {
+        Ok(Bluetooth { conn: Connection::system().await?, service_down: AtomicBool::new(false), low_battery: Mutex::new(HashSet::new()) })
+    }
+
+    /// The devices that just dropped below `threshold` percent.
+    fn newly_low<'a>(&self, devices: &'a [Device], threshold: u8) -> Vec<&'a Device> {
+        let mut low_battery = self.low_battery.lock().unwrap();
+        devices
+            .iter()
+            .filter(|device| match device.battery {
+                Some(pct) if pct < threshold => low_battery.insert(device.name.clone()),
+                Some(_) => {
+                    low_battery.remove(&device.name);
+                    false
+                }
+                None => false,
+            })
+            .collect()
+    }
+
+    /// Sends `id` on the trigger channel whenever a device's `Connected` property changes, so the
+    /// module updates right away instead of on its next poll. Returns when the bus connection ends.
+    pub async fn watch_connections(&self, id: ModuleId, trigger_tx: broadcast::Sender<ModuleId>) -> Result<()> {
+        let rule = MatchRule::builder()
+            .msg_type(message::Type::Signal)
+            .sender("org.bluez")?
+            .interface("org.freedesktop.DBus.Properties")?
+            .member("PropertiesChanged")?
+            .add_arg(DEVICE_IFACE)?
+            .build();
+        let mut stream = MessageStream::for_match_rule(rule, &self.conn, None).await?;
+        while let Some(msg) = stream.next().await {
+            let msg = msg?;
+            let (_, changed, _): (String, HashMap<String, OwnedValue>, Vec<String>) = msg.body().deserialize()?;
+            if changed.contains_key("Connected") {
+                tracing::debug!(target: "monitor::bluetooth", path = ?msg.header().path(), "Device connection changed");
+                let _ = trigger_tx.send(id.clone());
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn connected_devices(&self) -> Result<Vec<Device>> {
+        let manager = ObjectManagerProxy::builder(&self.conn).destination("org.bluez")?.path("/")?.build().await?;
+        let objects = manager.get_managed_objects().await?;
+        let mut devices = Vec::new();
+        for interfaces in objects.values() {
+            let Some(device) = interfaces.iter().find(|(name, _)| name.as_str() == DEVICE_IFACE).map(|(_, props)| props) else {
+                continue;
+            };
+            if !prop::<bool>(device, "Connected").unwrap_or(false) {
+                continue;
+            }
+            let battery = interfaces
+                .iter()
+                .find(|(name, _)| name.as_str() == BATTERY_IFACE)
+                .and_then(|(_, props)| prop::<u8>(props, "Percentage"));
+            devices.push(Device {
+                name: prop::<String>(device, "Alias").or_else(|| prop(device, "Name")).unwrap_or_default(),
+                icon: prop::<String>(device, "Icon").unwrap_or_default(),
+                battery,
+            });
+        }
+        Ok(devices)
+    }
+}
+
+fn prop<T>(props: &HashMap<String, OwnedValue>, key: &str) -> Option<T>
+where
+    T: TryFrom<OwnedValue>,
+{
+    props.get(key).and_then(|v| v.try_clone().ok()).and_then(|v| T::try_from(v).ok())
+}
+
+/// Where a device goes when devices are cut to `max_devices`: audio first, then input devices.
+fn icon_rank(icon: &str) -> u8 {
+    if icon.starts_with("audio-") {
+        0
+    } else if icon.starts_with("input-") {
+        1
+    } else {
+        2
+    }
+}
+
+/// Maps a BlueZ `Icon` property (freedesktop icon name) to a glyph.
+pub fn icon_glyph(icon: &str) -> &'static str {
+    match icon {
+        "audio-headset" | "audio-headphones" | "audio-card" => "🎧",
+        "input-mouse" => "🖱️",
+        "input-keyboard" => "⌨️",
+        _ => "ᛒ",
+    }
+}
+
+/// Whether the error says nothing owns `org.bluez`, i.e. the bluetooth service is stopped.
+fn is_service_unknown(e: &anyhow::Error) -> bool {
+    match e.downcast_ref::<zbus::Error>() {
+        Some(zbus::Error::FDO(e)) => matches!(**e, zbus::fdo::Error::ServiceUnknown(_)),
+        Some(zbus::Error::MethodError(name, _, _)) => name.as_str() == "org.freedesktop.DBus.Error.ServiceUnknown",
+        Some(_) => false,
+        None => matches!(e.downcast_ref::<zbus::fdo::Error>(), Some(zbus::fdo::Error::ServiceUnknown(_))),
+    }
+}
+
+/// Shows up to `max_devices` connected devices, e.g. `bt: 🎧 Headphones 82% | 🖱️ Mouse 45%`, and
+/// how many more there are. Hides the module while BlueZ isn't running instead of failing, since
+/// it may be started later; the next poll picks it up again. With `notify`, a device dropping
+/// below `low_battery_pct` is announced with notify-send.
+pub async fn monitor(bt: &Bluetooth, config: &BluetoothConfig, notify: bool) -> Result<String> {
+    let mut devices = match bt.connected_devices().await {
+        Ok(devices) => {
+            if bt.service_down.swap(false, Ordering::Relaxed) {
+                tracing::info!(target: "monitor::bluetooth", "BlueZ is back");
+            }
+            devices
+        }
+        Err(e) if is_service_unknown(&e) => {
+            if !bt.service_down.swap(true, Ordering::Relaxed) {
+                tracing::info!(target: "monitor::bluetooth", "BlueZ is not running, hiding the module until it starts");
+            }
+            return Ok(String::new());
+        }
+        Err(e) => return Err(e),
+    };
+    tracing::debug!(target: "monitor::bluetooth", ?devices, "Queried BlueZ");
+    if devices.is_empty() {
+        return Ok(String::new());
+    }
+    for device in bt.newly_low(&devices, config.low_battery_pct) {
+        let summary = format!("{} battery low", device.name);
+        let body = format!("{}%, below the {}% threshold", device.battery.unwrap_or_default(), config.low_battery_pct);
+        tracing::info!(target: "monitor::bluetooth", "{}: {}", summary, body);
+        if notify {
+            if let Err(e) = crate::run_command("notify-send", &[&summary, &body]).await {
+                tracing::warn!(target: "monitor::bluetooth", "Failed to send low battery notification: {}", e);
+            }
+        }
+    }
+    // BlueZ lists devices in no particular order; the name keeps the order stable between polls.
+    devices.sort_by(|a, b| icon_rank(&a.icon).cmp(&icon_rank(&b.icon)).then_with(|| a.name.cmp(&b.name)));
+    let shown = config.max_devices.clamp(1, devices.len());
+    let parts: Vec<String> = devices[..shown]
+        .iter()
+        .map(|device| {
+            let glyph = icon_glyph(&device.icon);
+            let Some(pct) = device.battery else {
+                return format!("{} {}", glyph, device.name);
+            };
+            let low = if pct < config.low_battery_pct { "🪫 " } else { "" };
+            if config.show_battery_bar {
+                format!("{} {} {}{} {}%", glyph, device.name, low, percent_to_bar(pct, BATTERY_BAR_WIDTH), pct)
+            } else {
+                format!("{} {} {}{}%", glyph, device.name, low, pct)
+            }
+        })
+        .collect();
+    let more = devices.len() - shown;
+    Ok(match more {
+        0 => format!("bt: {}", parts.join(" | ")),
+        more => format!("bt: {} +{}", parts.join(" | "), more),
+    })
+}
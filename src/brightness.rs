@@ -0,0 +1,61 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const BACKLIGHT_DIR: &str = "/sys/class/backlight";
+/// VCP feature code of the brightness setting in the DDC/CI monitor control command set.
+const VCP_BRIGHTNESS: &str = "10";
+
+/// The first backlight device in name order, e.g. `/sys/class/backlight/intel_backlight`.
+pub fn backlight() -> Option<PathBuf> {
+    let mut devices: Vec<PathBuf> = fs::read_dir(BACKLIGHT_DIR).ok()?.flatten().map(|entry| entry.path()).collect();
+    devices.sort();
+    devices.into_iter().next()
+}
+
+fn read_number(path: &Path) -> Result<u64> {
+    let text = fs::read_to_string(path).with_context(|| format!("Cannot read {}", path.display()))?;
+    text.trim().parse().with_context(|| format!("Invalid number in {}", path.display()))
+}
+
+/// The internal display's brightness from sysfs, e.g. `bri: 80%`.
+pub async fn monitor(device: &Path) -> Result<String> {
+    let current = read_number(&device.join("brightness"))?;
+    let max = read_number(&device.join("max_brightness"))?;
+    anyhow::ensure!(max > 0, "{} has a max_brightness of 0", device.display());
+    Ok(format!("bri: {}%", (current * 100 + max / 2) / max))
+}
+
+/// The display numbers in `ddcutil detect --brief` output, which lists `Display 1`, `Display 2`,
+/// ... for monitors that answer DDC/CI and `Invalid display` for those that don't.
+fn displays(detected: &str) -> Vec<&str> {
+    detected.lines().filter_map(|line| line.strip_prefix("Display ")).map(str::trim).collect()
+}
+
+/// Percent from `ddcutil getvcp 10 --brief` output, `VCP 10 C <current> <max>`.
+fn parse_vcp(output: &str) -> Option<u64> {
+    let fields: Vec<&str> = output.split_whitespace().collect();
+    let [_, _, "C", current, max] = fields[..] else {
+        return None;
+    };
+    let (current, max): (u64, u64) = (current.parse().ok()?, max.parse().ok()?);
+    (max > 0).then(|| (current * 100 + max / 2) / max)
+}
+
+/// The brightness of every external monitor over DDC/CI, e.g. `ext: 60%` or `ext: 60% 45%`.
+/// Monitors are detected on each run, so ones plugged in later show up; the module is hidden
+/// without any. Every query talks to the monitor over I2C, which is why it polls slowly.
+pub async fn external_monitor() -> Result<String> {
+    let detected = crate::run_command("ddcutil", &["detect", "--brief"]).await?;
+    let mut levels = Vec::new();
+    for display in displays(&detected) {
+        let output = crate::run_command("ddcutil", &["--display", display, "getvcp", VCP_BRIGHTNESS, "--brief"]).await?;
+        let percent = parse_vcp(&output).with_context(|| format!("No brightness in ddcutil output for display {}: {}", display, output))?;
+        levels.push(format!("{}%", percent));
+    }
+    tracing::debug!(target: "monitor::ext_brightness", ?levels, "Queried DDC/CI monitors");
+    if levels.is_empty() {
+        return Ok(String::new());
+    }
+    Ok(format!("ext: {}", levels.join(" ")))
+}
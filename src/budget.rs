@@ -0,0 +1,148 @@
+use crate::config::Config;
+use crate::registry::{ModuleId, ModuleRegistry};
+use std::collections::HashMap;
+
+/// A module cut shorter than this is dropped instead, since a stub like `d…` says nothing.
+const MIN_TRUNCATED_CHARS: usize = 5;
+
+/// Width limits for text sinks: each module's `max_len`, then the `max_bar_chars` limit and the
+/// module priorities used to meet it.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    max_chars: Option<usize>,
+    max_lens: HashMap<ModuleId, usize>,
+    priorities: HashMap<ModuleId, i32>,
+}
+
+impl Budget {
+    /// `None` when no limit is configured. A module's priority defaults to its position in the
+    /// display order, so the rightmost modules (the clock, by default) are kept longest.
+    pub fn new(config: &Config, registry: &ModuleRegistry) -> Option<Self> {
+        let max_lens: HashMap<ModuleId, usize> =
+            registry.order().iter().filter_map(|id| Some((id.clone(), config.module(id).max_len?))).collect();
+        if config.max_bar_chars.is_none() && max_lens.is_empty() {
+            return None;
+        }
+        let priorities = registry
+            .order()
+            .iter()
+            .enumerate()
+            .map(|(position, id)| (id.clone(), config.module(id).priority.unwrap_or(position as i32)))
+            .collect();
+        Some(Budget { max_chars: config.max_bar_chars, max_lens, priorities })
+    }
+
+    /// Applies the budget to modules in display order. `overhead` is the width of anything the
+    /// caller adds around the joined modules, `separator_width` that of what goes between two.
+    pub fn fit(&self, parts: Vec<(ModuleId, String)>, overhead: usize, separator_width: &dyn Fn(&ModuleId, &ModuleId) -> usize) -> Vec<(ModuleId, String)> {
+        let parts = parts.into_iter().map(|(id, text)| {
+            let text = match self.max_lens.get(&id) {
+                Some(&max_len) => crate::util::truncate(&text, max_len),
+                None => text,
+            };
+            let priority = self.priorities.get(&id).copied().unwrap_or(i32::MAX);
+            (id, text, priority)
+        });
+        match self.max_chars {
+            Some(max_chars) => fit(parts.collect(), max_chars.saturating_sub(overhead), separator_width),
+            None => parts.map(|(id, text, _)| (id, text)).collect(),
+        }
+    }
+}
+
+/// Drops or truncates the lowest-priority modules until the modules, with `separator_width`
+/// characters between each two neighbours, fit in `max_chars`. Ties go to the leftmost module.
+/// The highest-priority module is only truncated when it alone doesn't fit. Display order is
+/// preserved.
+pub fn fit(
+    mut parts: Vec<(ModuleId, String, i32)>,
+    max_chars: usize,
+    separator_width: &dyn Fn(&ModuleId, &ModuleId) -> usize,
+) -> Vec<(ModuleId, String)> {
+    let width = |parts: &[(ModuleId, String, i32)]| -> usize {
+        let texts: usize = parts.iter().map(|(_, text, _)| text.chars().count()).sum();
+        texts + parts.windows(2).map(|pair| separator_width(&pair[0].0, &pair[1].0)).sum::<usize>()
+    };
+    loop {
+        let total = width(&parts);
+        if total <= max_chars || parts.is_empty() {
+            break;
+        }
+        let excess = total - max_chars;
+        let Some(lowest) = parts.iter().enumerate().min_by_key(|(position, (_, _, priority))| (*priority, *position)).map(|(i, _)| i) else {
+            break;
+        };
+        let alone = parts.len() == 1;
+        let text = &mut parts[lowest].1;
+        let len = text.chars().count();
+        if alone {
+            *text = crate::util::truncate(text, max_chars);
+        } else if len >= excess + MIN_TRUNCATED_CHARS {
+            *text = crate::util::truncate(text, len - excess);
+        } else {
+            parts.remove(lowest);
+        }
+    }
+    parts.into_iter().map(|(id, text, _)| (id, text)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(modules: &[(&str, &str, i32)]) -> Vec<(ModuleId, String, i32)> {
+        modules.iter().map(|&(id, text, priority)| (ModuleId::from(id), text.to_string(), priority)).collect()
+    }
+
+    /// The default ` | ` everywhere.
+    const THREE: fn(&ModuleId, &ModuleId) -> usize = |_, _| 3;
+
+    fn ids(fitted: &[(ModuleId, String)]) -> Vec<&str> {
+        fitted.iter().map(|(id, _)| &**id).collect()
+    }
+
+    #[test]
+    fn everything_fits() {
+        let fitted = fit(parts(&[("a", "aaaa", 0), ("b", "bbbb", 1)]), 11, &THREE);
+        assert_eq!(fitted, vec![("a".into(), "aaaa".to_string()), ("b".into(), "bbbb".to_string())]);
+    }
+
+    #[test]
+    fn drops_lowest_priority_first() {
+        // 4 + 3 + 4 + 3 + 4 = 18 characters; 11 leave room for two modules.
+        let fitted = fit(parts(&[("a", "aaaa", 2), ("b", "bbbb", 0), ("c", "cccc", 1)]), 11, &THREE);
+        assert_eq!(ids(&fitted), ["a", "c"]);
+        let fitted = fit(parts(&[("a", "aaaa", 2), ("b", "bbbb", 0), ("c", "cccc", 1)]), 4, &THREE);
+        assert_eq!(ids(&fitted), ["a"]);
+    }
+
+    #[test]
+    fn equal_priorities_drop_leftmost_and_keep_order() {
+        let fitted = fit(parts(&[("a", "aaaa", 0), ("b", "bbbb", 0), ("c", "cccc", 0)]), 11, &THREE);
+        assert_eq!(ids(&fitted), ["b", "c"]);
+    }
+
+    #[test]
+    fn truncates_when_enough_is_left() {
+        // One character over: the lowest-priority module loses its tail instead of disappearing.
+        let fitted = fit(parts(&[("a", "aaaaaaaa", 0), ("b", "bbbb", 1)]), 14, &THREE);
+        assert_eq!(fitted[0].1, "aaaaaa…");
+        assert_eq!(fitted[1].1, "bbbb");
+    }
+
+    #[test]
+    fn highest_priority_alone_too_wide_is_truncated() {
+        let fitted = fit(parts(&[("a", "aaaa", 0), ("clock", "Thu 15 Oct 10:44", 1)]), 6, &THREE);
+        assert_eq!(fitted, vec![("clock".into(), "Thu 1…".to_string())]);
+        assert!(fit(parts(&[("clock", "Thu 15 Oct", 1)]), 0, &THREE).iter().all(|(_, text)| text.is_empty()));
+    }
+
+    #[test]
+    fn uses_the_actual_separator_widths() {
+        // `a · b | c`: a and b share a group, so only one character sits between them.
+        let separator = |a: &ModuleId, b: &ModuleId| if (&**a, &**b) == ("a", "b") { 1 } else { 3 };
+        let modules = [("a", "aaaa", 2), ("b", "bbbb", 0), ("c", "cccc", 1)];
+        assert_eq!(ids(&fit(parts(&modules), 16, &separator)), ["a", "b", "c"]);
+        assert_eq!(ids(&fit(parts(&modules), 16, &THREE)), ["a", "c"]);
+    }
+}
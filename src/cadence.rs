@@ -0,0 +1,95 @@
+use crate::bar::Update;
+use crate::supervisor::TaskFactory;
+use crate::{spawn_monitor, MonitorContext};
+use anyhow::Result;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::Instrument;
+
+/// Spawns a module with two cadences: `fetch` does the expensive work (running an external tool)
+/// every `fetch_interval` and whenever the module is triggered, while `render` turns the cached
+/// data into display text every `render_interval`. This lets time-relative output such as
+/// countdowns tick between fetches without re-running the tool. A zero `fetch_interval` fetches
+/// once and then only on triggers.
+pub fn spawn_two_cadence<T, F, Fut, R>(
+    id: &str,
+    fetch_interval: Duration,
+    render_interval: Duration,
+    fetch: F,
+    render: R,
+    ctx: &MonitorContext,
+) where
+    T: Send + 'static,
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<T>> + Send,
+    R: Fn(Option<&T>) -> String + Send + Sync + 'static,
+{
+    let Some(module_id) = ctx.registry.get(id) else {
+        return;
+    };
+    let cache: Arc<Mutex<Option<T>>> = Arc::new(Mutex::new(None));
+    let render = Arc::new(render);
+
+    // A dry run only needs the first fetch, which renders its result too.
+    if !ctx.dry_run {
+        let (render_cache, render_fn) = (cache.clone(), render.clone());
+        spawn_monitor(
+            id,
+            render_interval,
+            move || {
+                let text = render_fn(render_cache.lock().unwrap().as_ref());
+                async move { Ok(text) }
+            },
+            ctx,
+        );
+    }
+
+    let fetch = Arc::new(fetch);
+    let command_timeout = Duration::from_millis(ctx.config.module(id).command_timeout_ms);
+    let (update_tx, trigger_tx, task_id, dry_run) = (ctx.update_tx.clone(), ctx.trigger_tx.clone(), module_id.clone(), ctx.dry_run);
+    let shutdown = ctx.shutdown.clone();
+    let factory: TaskFactory = Arc::new(move || {
+        let (module_id, tx, fetch, cache, render) = (task_id.clone(), update_tx.clone(), fetch.clone(), cache.clone(), render.clone());
+        let shutdown = shutdown.clone();
+        let mut trigger_rx = trigger_tx.subscribe();
+        let span = tracing::info_span!("module", module_id = %module_id);
+        Box::pin(async move {
+            loop {
+                match crate::command::MODULE_TIMEOUT.scope(command_timeout, fetch()).await {
+                    Ok(data) => {
+                        let text = {
+                            let mut cache = cache.lock().unwrap();
+                            *cache = Some(data);
+                            render(cache.as_ref())
+                        };
+                        if tx.send(Update::success(module_id.clone(), text)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!(target: "monitor", "Fetch for '{}' failed: {}", module_id, e);
+                        if tx.send(Update::failure(module_id.clone(), &e)).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+                if dry_run {
+                    break;
+                }
+                let sleep = tokio::time::sleep(fetch_interval);
+                tokio::pin!(sleep);
+                loop {
+                    tokio::select! {
+                        _ = shutdown.cancelled() => return,
+                        _ = &mut sleep, if !fetch_interval.is_zero() => break,
+                        Ok(triggered_id) = trigger_rx.recv() => {
+                            if triggered_id == module_id { break; }
+                        }
+                    }
+                }
+            }
+        }.instrument(span))
+    });
+    ctx.supervisor.spawn(module_id, factory);
+}
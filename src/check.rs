@@ -0,0 +1,181 @@
+use crate::colors::XRES_PREFIX;
+use crate::config::{Config, SinkConfig};
+use crate::registry::{LEFT_END, RIGHT_END};
+use regex::Regex;
+
+/// sysinfo's `MINIMUM_CPU_UPDATE_INTERVAL` on the platforms that have one.
+const MIN_CPU_SAMPLE_PERIOD_MS: u64 = 200;
+/// The sysinfo thread is busy for the whole sample period, so other modules wait behind it.
+const MAX_CPU_SAMPLE_PERIOD_MS: u64 = 2000;
+
+/// Validates what deserializing can't: module IDs, value ranges, colors, format strings and
+/// patterns. `known` lists every module ID the bar knows on this system. Each problem is
+/// reported with the config key it was found at.
+pub fn check(config: &Config, known: &[&str]) -> Vec<String> {
+    let mut errors = Vec::new();
+    let is_known = |id: &str| known.contains(&id) || is_gpu_zone(id);
+    let check_id = |errors: &mut Vec<String>, key: String, id: &str| {
+        if !is_known(id) {
+            errors.push(format!("{}: unknown module '{}'", key, id));
+        }
+    };
+
+    for (i, id) in config.order.iter().flatten().enumerate() {
+        if id != LEFT_END && id != RIGHT_END {
+            check_id(&mut errors, format!("order[{}]", i), id);
+        }
+    }
+    for (i, group) in config.groups.iter().enumerate() {
+        for (j, id) in group.iter().enumerate() {
+            check_id(&mut errors, format!("groups[{}][{}]", i, j), id);
+        }
+    }
+    for (i, id) in config.minimal_modules.iter().enumerate() {
+        check_id(&mut errors, format!("minimal_modules[{}]", i), id);
+    }
+    for (i, sink) in config.sinks.iter().enumerate() {
+        for (j, id) in sink.modules().into_iter().flatten().enumerate() {
+            check_id(&mut errors, format!("sinks[{}].modules[{}]", i, j), id);
+        }
+        if let SinkConfig::File { path, .. } = sink {
+            if path.as_os_str().is_empty() {
+                errors.push(format!("sinks[{}].path: must not be empty", i));
+            }
+        }
+    }
+
+    if config.update_queue_capacity == 0 {
+        errors.push("update_queue_capacity: must be positive".to_string());
+    }
+    if config.max_update_age_ms == 0 {
+        errors.push("max_update_age_ms: must be positive".to_string());
+    }
+    if config.max_bar_chars == Some(0) {
+        errors.push("max_bar_chars: must be positive".to_string());
+    }
+    if config.disk.smart_interval_secs == 0 {
+        errors.push("disk.smart_interval_secs: must be positive".to_string());
+    }
+    if !(MIN_CPU_SAMPLE_PERIOD_MS..=MAX_CPU_SAMPLE_PERIOD_MS).contains(&config.cpu_load.sample_period_ms) {
+        errors.push(format!("cpu_load.sample_period_ms: must be between {} and {}", MIN_CPU_SAMPLE_PERIOD_MS, MAX_CPU_SAMPLE_PERIOD_MS));
+    }
+    if config.bluetooth.low_battery_pct > 100 {
+        errors.push(format!("bluetooth.low_battery_pct: {} is not a percentage", config.bluetooth.low_battery_pct));
+    }
+    if config.low_power_mode_battery_pct > 100 {
+        errors.push(format!("low_power_mode_battery_pct: {} is not a percentage", config.low_power_mode_battery_pct));
+    }
+
+    let mut colors: Vec<(&String, &String)> = config.colors.iter().collect();
+    colors.sort();
+    for (name, spec) in colors {
+        if let Err(e) = check_color_spec(spec) {
+            errors.push(format!("colors.{}: {}", name, e));
+        }
+    }
+    let mut ids: Vec<&String> = config.modules.keys().collect();
+    ids.sort();
+    for id in ids {
+        let module = &config.modules[id];
+        let key = format!("modules.{}", id);
+        check_id(&mut errors, key.clone(), id);
+        if let Some(color) = &module.color {
+            if !config.colors.contains_key(color) {
+                if let Err(e) = check_color_spec(color) {
+                    errors.push(format!("{}.color: {} (and no [colors] entry is named '{}')", key, e, color));
+                }
+            }
+        }
+        for (i, &[start, end]) in module.visible_hours.iter().enumerate() {
+            if start > 23 || end > 24 {
+                errors.push(format!("{}.visible_hours[{}]: hours must be within 0-24", key, i));
+            }
+        }
+        if module.max_len == Some(0) {
+            errors.push(format!("{}.max_len: must be positive", key));
+        }
+        for (watched, pattern) in &module.hide_when {
+            check_id(&mut errors, format!("{}.hide_when", key), watched);
+            if let Err(e) = Regex::new(pattern) {
+                errors.push(format!("{}.hide_when.{}: invalid regex: {}", key, watched, e));
+            }
+        }
+    }
+
+    let datetime = &config.datetime;
+    if !valid_datetime_format(&datetime.format) {
+        errors.push(format!("datetime.format: invalid format '{}'", datetime.format));
+    }
+    if let Some(format) = datetime.secondary_format.as_deref().filter(|f| !valid_datetime_format(f)) {
+        errors.push(format!("datetime.secondary_format: invalid format '{}'", format));
+    }
+    if let Some(locale) = &datetime.locale {
+        let base = locale.split(['.', '@']).next().unwrap_or_default();
+        if base != "C" && base != "POSIX" && chrono::Locale::try_from(base).is_err() {
+            errors.push(format!("datetime.locale: unsupported locale '{}'", locale));
+        }
+    }
+    if let Some(target) = &datetime.countdown_to {
+        if let Err(e) = chrono::DateTime::parse_from_rfc3339(target) {
+            errors.push(format!("datetime.countdown_to: '{}' is not an RFC 3339 timestamp: {}", target, e));
+        }
+    }
+
+    for (i, disk) in config.disk.instances.iter().enumerate() {
+        for (field, threshold) in [("alert_threshold", disk.alert_threshold), ("inode_alert_threshold", disk.inode_alert_threshold)] {
+            if !(0.0..=100.0).contains(&threshold) {
+                errors.push(format!("disk.instances[{}].{}: {} is not a percentage", i, field, threshold));
+            }
+        }
+    }
+    for (name, temp) in [("cpu_temp", &config.cpu_temp), ("gpu_temp", &config.gpu_temp)] {
+        if temp.alert_hysteresis.is_some_and(|h| h < 0.0) {
+            errors.push(format!("{}.alert_hysteresis: must not be negative", name));
+        }
+    }
+    if config.agenda.horizon_hours <= 0 {
+        errors.push("agenda.horizon_hours: must be positive".to_string());
+    }
+    for (i, symbol) in config.ticker.symbols.iter().enumerate() {
+        if symbol.format.as_deref().is_some_and(|f| !f.contains("{value}")) {
+            errors.push(format!("ticker.symbols[{}].format: must contain {{value}}", i));
+        }
+    }
+    errors
+}
+
+/// Whether chrono can render `format`.
+pub fn valid_datetime_format(format: &str) -> bool {
+    use std::fmt::Write;
+    let mut probe = String::new();
+    write!(probe, "{}", chrono::Local::now().format(format)).is_ok()
+}
+
+/// GPU thermal zones are numbered at runtime, so any `gpu_temp_<n>` is accepted.
+fn is_gpu_zone(id: &str) -> bool {
+    id.strip_prefix("gpu_temp_").is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+}
+
+/// A color spec is `#rrggbb`, or `xres:<resource>` with an optional `|#rrggbb` fallback, where
+/// a `colorN` resource must be one of the 256 terminal colors.
+fn check_color_spec(spec: &str) -> Result<(), String> {
+    let Some(rest) = spec.strip_prefix(XRES_PREFIX) else {
+        return check_hex(spec);
+    };
+    let (name, fallback) = match rest.split_once('|') {
+        Some((name, fallback)) => (name, Some(fallback)),
+        None => (rest, None),
+    };
+    let index = name.trim_start_matches('*').trim_start_matches('.').strip_prefix("color").and_then(|n| n.parse::<u32>().ok());
+    if index.is_some_and(|n| n > 255) {
+        return Err(format!("'{}' is out of range, Xresources colors are color0-color255", name));
+    }
+    fallback.map_or(Ok(()), check_hex)
+}
+
+fn check_hex(color: &str) -> Result<(), String> {
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.bytes().all(|b| b.is_ascii_hexdigit()) => Ok(()),
+        _ => Err(format!("'{}' is not a #rrggbb color", color)),
+    }
+}
@@ -0,0 +1,138 @@
+use crate::registry::{ModuleId, ModuleRegistry};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::broadcast;
+
+/// How often the wall clock is compared with the monotonic clock.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// Differences below this are scheduling noise, not a clock change.
+const JUMP_THRESHOLD: Duration = Duration::from_secs(2);
+/// How often NTP synchronization is checked, independently of the clock's own ticks.
+#[cfg(target_os = "linux")]
+const NTP_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Detects wall clock discontinuities (NTP corrections, manual changes, resume from suspend) by
+/// comparing how far `SystemTime` and `Instant` moved between two readings.
+pub struct ClockWatch {
+    last: (Instant, SystemTime),
+    /// When the last jump was detected. Updates are not older than this for staleness.
+    jumped: Option<Instant>,
+}
+
+impl ClockWatch {
+    pub fn new(now: Instant, wall: SystemTime) -> Self {
+        ClockWatch { last: (now, wall), jumped: None }
+    }
+
+    /// Records a reading and returns how far the wall clock jumped relative to the monotonic
+    /// clock since the previous one (negative for a backwards jump), if beyond the threshold.
+    pub fn check(&mut self, now: Instant, wall: SystemTime) -> Option<f64> {
+        let (last_now, last_wall) = std::mem::replace(&mut self.last, (now, wall));
+        let monotonic = now.duration_since(last_now).as_secs_f64();
+        let wall = match wall.duration_since(last_wall) {
+            Ok(forward) => forward.as_secs_f64(),
+            Err(e) => -e.duration().as_secs_f64(),
+        };
+        let jump = wall - monotonic;
+        let jumped = jump.abs() > JUMP_THRESHOLD.as_secs_f64();
+        if jumped {
+            self.jumped = Some(now);
+        }
+        jumped.then_some(jump)
+    }
+
+    /// How old an update created at `created` is, counted from the last jump at the earliest so
+    /// values computed around a suspend or clock change aren't dropped as stale.
+    pub fn age(&self, created: Instant, now: Instant) -> Duration {
+        let since = self.jumped.map_or(created, |jumped| created.max(jumped));
+        now.saturating_duration_since(since)
+    }
+}
+
+/// Refreshes every module when the wall clock jumps, so clocks and countdowns don't show
+/// pre-jump values until their next run.
+pub async fn watch(trigger_tx: broadcast::Sender<ModuleId>, registry: Arc<ModuleRegistry>, clock: Arc<Mutex<ClockWatch>>) {
+    let mut interval = tokio::time::interval(CHECK_INTERVAL);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        let jump = clock.lock().unwrap().check(Instant::now(), SystemTime::now());
+        if let Some(jump) = jump {
+            tracing::info!("Wall clock jumped by {:+.1}s, refreshing all modules", jump);
+            for id in registry.order() {
+                let _ = trigger_tx.send(id.clone());
+            }
+        }
+    }
+}
+
+/// Whether the kernel considers the clock synchronized. This is the flag behind `timedatectl`'s
+/// `NTPSynchronized`, maintained by chrony, ntpd and systemd-timesyncd alike, read without
+/// spawning anything.
+#[cfg(target_os = "linux")]
+fn ntp_synchronized() -> bool {
+    // SAFETY: with `modes` zeroed, adjtimex only reads the clock state into the struct.
+    let mut timex: libc::timex = unsafe { std::mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut timex) };
+    state >= 0 && timex.status & libc::STA_UNSYNC == 0
+}
+
+/// Keeps `unsynced` current for the datetime module's `(!)` marker.
+#[cfg(target_os = "linux")]
+pub async fn watch_ntp(unsynced: Arc<std::sync::atomic::AtomicBool>) {
+    let mut interval = tokio::time::interval(NTP_CHECK_INTERVAL);
+    loop {
+        interval.tick().await;
+        let now_unsynced = !ntp_synchronized();
+        if unsynced.swap(now_unsynced, std::sync::atomic::Ordering::Relaxed) != now_unsynced {
+            tracing::info!(target: "monitor::datetime", "System clock is {}", if now_unsynced { "not NTP-synchronized" } else { "NTP-synchronized again" });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    fn start() -> (ClockWatch, Instant, SystemTime) {
+        let (now, wall) = (Instant::now(), SystemTime::now());
+        (ClockWatch::new(now, wall), now, wall)
+    }
+
+    #[test]
+    fn no_jump_when_both_clocks_move_together() {
+        let (mut clock, now, wall) = start();
+        assert_eq!(clock.check(now + SECOND, wall + SECOND), None);
+        // Scheduling noise below the threshold is not a jump either.
+        assert_eq!(clock.check(now + 2 * SECOND, wall + 3 * SECOND), None);
+        let created = now + SECOND;
+        assert_eq!(clock.age(created, now + 10 * SECOND), 9 * SECOND);
+    }
+
+    #[test]
+    fn forward_jump() {
+        let (mut clock, now, wall) = start();
+        assert_eq!(clock.check(now + SECOND, wall + 61 * SECOND), Some(60.0));
+        // The next reading compares against the post-jump one.
+        assert_eq!(clock.check(now + 2 * SECOND, wall + 62 * SECOND), None);
+    }
+
+    #[test]
+    fn backward_jump() {
+        let (mut clock, now, wall) = start();
+        assert_eq!(clock.check(now + SECOND, wall - 59 * SECOND), Some(-60.0));
+    }
+
+    #[test]
+    fn jump_resets_the_age_baseline() {
+        let (mut clock, now, wall) = start();
+        let created = now;
+        assert!(clock.check(now + 30 * SECOND, wall + 3600 * SECOND).is_some());
+        // Counted from the jump, not from when the update was created.
+        assert_eq!(clock.age(created, now + 31 * SECOND), SECOND);
+        // Updates created after the jump keep their own age.
+        assert_eq!(clock.age(now + 40 * SECOND, now + 45 * SECOND), 5 * SECOND);
+    }
+}
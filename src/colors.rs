@@ -0,0 +1,88 @@
+use crate::config::Config;
+use crate::registry::{ModuleId, ModuleRegistry};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Resolved status2d colors (`#rrggbb`) keyed by module, shared with the main loop.
+pub type ModuleColors = Arc<Mutex<HashMap<ModuleId, String>>>;
+
+pub const XRES_PREFIX: &str = "xres:";
+
+/// Resolves every module's `color` setting. A setting may name an entry of the `[colors]` palette
+/// or be a color spec itself: a literal like `#ff5555`, or `xres:<resource>` with an optional
+/// `|#rrggbb` fallback for when the resource is missing.
+pub async fn resolve(config: &Config, registry: &ModuleRegistry) -> HashMap<ModuleId, String> {
+    let specs: Vec<(ModuleId, &str)> = registry
+        .order()
+        .iter()
+        .filter_map(|id| {
+            let color = config.modules.get(&**id)?.color.as_deref()?;
+            Some((id.clone(), config.colors.get(color).map(String::as_str).unwrap_or(color)))
+        })
+        .collect();
+
+    let xresources = if specs.iter().any(|(_, spec)| spec.starts_with(XRES_PREFIX)) {
+        query_xresources().await
+    } else {
+        HashMap::new()
+    };
+
+    let mut missing = Vec::new();
+    let mut resolved = HashMap::new();
+    for (id, spec) in specs {
+        let Some(rest) = spec.strip_prefix(XRES_PREFIX) else {
+            resolved.insert(id, spec.to_string());
+            continue;
+        };
+        let (name, fallback) = match rest.split_once('|') {
+            Some((name, fallback)) => (name, Some(fallback)),
+            None => (rest, None),
+        };
+        let found = lookup(&xresources, name);
+        if found.is_none() {
+            missing.push(name);
+        }
+        if let Some(color) = found.or(fallback) {
+            resolved.insert(id, color.to_string());
+        }
+    }
+    if !missing.is_empty() {
+        tracing::warn!("Xresources missing {}, using fallbacks where configured", missing.join(", "));
+    }
+    resolved
+}
+
+/// Finds `name` either verbatim (`dwm.normfgcolor`) or as a wildcard resource (`*.color1`, `*color1`).
+fn lookup<'a>(xresources: &'a HashMap<String, String>, name: &str) -> Option<&'a str> {
+    [name.to_string(), format!("*.{}", name), format!("*{}", name)]
+        .iter()
+        .find_map(|key| xresources.get(key))
+        .map(String::as_str)
+}
+
+async fn query_xresources() -> HashMap<String, String> {
+    match crate::run_command("xrdb", &["-query"]).await {
+        Ok(output) => output
+            .lines()
+            .filter_map(|line| line.split_once(':'))
+            .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Cannot query Xresources: {}", e);
+            HashMap::new()
+        }
+    }
+}
+
+/// Re-reads Xresources on SIGHUP so a theme switch reaches the bar without a restart.
+pub async fn reload_on_sighup(colors: ModuleColors, config: Arc<Config>, registry: Arc<ModuleRegistry>) -> anyhow::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sighup = signal(SignalKind::hangup())?;
+    while sighup.recv().await.is_some() {
+        tracing::info!("SIGHUP received, reloading colors from Xresources");
+        let resolved = resolve(&config, &registry).await;
+        *colors.lock().unwrap() = resolved;
+    }
+    Ok(())
+}
@@ -0,0 +1,174 @@
+use crate::util::truncate;
+use regex::Regex;
+use std::fmt;
+use std::io;
+use std::process::Stdio;
+use std::sync::LazyLock;
+use std::time::Duration;
+
+/// Stderr kept in a `CommandError`, in characters. Some tools print pages on failure.
+const MAX_STDERR: usize = 200;
+/// How long a command may run when neither the caller nor the running module sets a limit.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Terminal escape sequences: CSI (colors, cursor movement), OSC (titles, hyperlinks) terminated
+/// by BEL or ST, and the remaining two-character escapes.
+static ANSI_ESCAPE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)|[@-Z\\-_])").unwrap());
+
+tokio::task_local! {
+    /// The running module's `command_timeout_ms`, set around each of its runs.
+    pub static MODULE_TIMEOUT: Duration;
+}
+
+/// Why an external command failed, kept structured so the bar can tell a missing tool from a
+/// transient failure.
+#[derive(Debug, Clone)]
+pub enum CommandError {
+    /// The command could not be started at all, e.g. it is not installed.
+    Spawn { cmd: String, kind: io::ErrorKind },
+    /// The command ran and exited unsuccessfully. `code` is `None` when it was killed by a signal.
+    Exit { cmd: String, code: Option<i32>, stderr: String },
+    /// The command was killed after running longer than its timeout.
+    Timeout { cmd: String, after: Duration },
+}
+
+impl CommandError {
+    /// Whether the command does not exist, which retrying won't fix.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, CommandError::Spawn { kind: io::ErrorKind::NotFound, .. })
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        match self {
+            CommandError::Exit { code, .. } => *code,
+            CommandError::Spawn { .. } | CommandError::Timeout { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Spawn { cmd, kind: io::ErrorKind::NotFound } => write!(f, "Command '{}' not found, is it installed?", cmd),
+            CommandError::Spawn { cmd, kind } => write!(f, "Command '{}' could not be started: {}", cmd, kind),
+            CommandError::Exit { cmd, code: Some(code), stderr } => write!(f, "Command '{}' exited with {}: {}", cmd, code, stderr),
+            CommandError::Exit { cmd, code: None, stderr } => write!(f, "Command '{}' was killed: {}", cmd, stderr),
+            CommandError::Timeout { cmd, after } => write!(f, "Command '{}' timed out after {:?} and was killed", cmd, after),
+        }
+    }
+}
+
+impl std::error::Error for CommandError {}
+
+/// Runs a command with the running module's timeout, or `DEFAULT_TIMEOUT` outside a module.
+pub async fn run_command(cmd: &str, args: &[&str]) -> Result<String, CommandError> {
+    run_command_timeout(cmd, args, None).await
+}
+
+/// Like `run_command`, but strips terminal escape sequences such as colors from the output, for
+/// scripts that print them even when stdout isn't a terminal.
+pub async fn run_command_clean(cmd: &str, args: &[&str]) -> Result<String, CommandError> {
+    run_command(cmd, args).await.map(|output| strip_ansi(&output))
+}
+
+pub fn strip_ansi(text: &str) -> String {
+    ANSI_ESCAPE.replace_all(text, "").trim().to_string()
+}
+
+/// Runs a command and returns its trimmed stdout. The child is killed if it is still running
+/// after `timeout` (by default the module's, see `run_command`). A zero timeout waits forever.
+pub async fn run_command_timeout(cmd: &str, args: &[&str], timeout: Option<Duration>) -> Result<String, CommandError> {
+    let timeout = timeout.or_else(|| MODULE_TIMEOUT.try_with(|t| *t).ok()).unwrap_or(DEFAULT_TIMEOUT);
+    let spawn_error = |e: io::Error| CommandError::Spawn { cmd: cmd.to_string(), kind: e.kind() };
+    let child = tokio::process::Command::new(cmd)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        // Its own process group, so a timeout also kills what a wrapper script started.
+        .process_group(0)
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(spawn_error)?;
+    let pid = child.id();
+    let output = if timeout.is_zero() {
+        child.wait_with_output().await.map_err(spawn_error)?
+    } else {
+        match tokio::time::timeout(timeout, child.wait_with_output()).await {
+            Ok(output) => output.map_err(spawn_error)?,
+            Err(_) => {
+                // Dropping the child killed it; this takes its children along.
+                if let Some(pid) = pid {
+                    // SAFETY: killpg only sends a signal; the group is the one created above.
+                    unsafe { libc::killpg(pid as libc::pid_t, libc::SIGKILL) };
+                }
+                return Err(CommandError::Timeout { cmd: cmd.to_string(), after: timeout });
+            }
+        }
+    };
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    } else {
+        Err(CommandError::Exit {
+            cmd: cmd.to_string(),
+            code: output.status.code(),
+            stderr: truncate(&strip_ansi(&String::from_utf8_lossy(&output.stderr)), MAX_STDERR),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// Whether `pid` is still running; a killed process may linger as a zombie until reaped.
+    fn running(pid: &str) -> bool {
+        std::fs::read_to_string(format!("/proc/{}/stat", pid)).is_ok_and(|stat| !stat.contains(") Z "))
+    }
+
+    #[tokio::test]
+    async fn timeout_kills_the_command() {
+        let started = Instant::now();
+        let result = run_command_timeout("sleep", &["10"], Some(Duration::from_millis(100))).await;
+        assert!(matches!(result, Err(CommandError::Timeout { after, .. }) if after == Duration::from_millis(100)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn timeout_kills_the_whole_process_group() {
+        let pid_file = std::env::temp_dir().join(format!("dwm-bar-test-{}.pid", std::process::id()));
+        let script = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        let result = run_command_timeout("sh", &["-c", &script], Some(Duration::from_millis(200))).await;
+        assert!(matches!(result, Err(CommandError::Timeout { .. })));
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let _ = std::fs::remove_file(&pid_file);
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while running(pid.trim()) && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!running(pid.trim()), "the background sleep survived the timeout");
+    }
+
+    #[tokio::test]
+    async fn error_output_is_stripped_of_escapes() {
+        let script = r"printf '\033[31mboom\033[0m\n' >&2; exit 3";
+        let error = run_command("sh", &["-c", script]).await.unwrap_err();
+        assert_eq!(error.exit_code(), Some(3));
+        assert_eq!(error.to_string(), "Command 'sh' exited with 3: boom");
+    }
+
+    #[tokio::test]
+    async fn missing_command() {
+        let error = run_command("dwm-bar-no-such-command", &[]).await.unwrap_err();
+        assert!(error.is_not_found());
+    }
+
+    #[test]
+    fn strips_csi_osc_and_short_escapes() {
+        assert_eq!(strip_ansi("\x1b[1;32mok\x1b[0m"), "ok");
+        assert_eq!(strip_ansi("\x1b]8;;https://example.com\x07link\x1b]8;;\x1b\\ "), "link");
+        assert_eq!(strip_ansi("a\x1bMb"), "ab");
+    }
+}
@@ -0,0 +1,762 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Top-level configuration, loaded from a TOML file passed via `--config` or found by
+/// `default_path`. Every field has a default so an empty or missing file behaves like the built-in setup.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Display order of module IDs. Defaults to the built-in order. Modules before `__left_end__`
+    /// or after `__right_end__` are pinned to that end of the bar on every sink.
+    pub order: Option<Vec<String>>,
+    /// Goes between the pinned sections and the rest instead of `group_separator`, e.g. a wider gap.
+    pub section_separator: Option<String>,
+    /// Related modules, e.g. `[["cpu_load", "ram", "disk"], ["cpu_temp", "battery"]]`. Modules of
+    /// one group are joined with `module_separator`; modules in no group form a group of their own.
+    pub groups: Vec<Vec<String>>,
+    /// Goes between groups.
+    pub group_separator: String,
+    /// Goes between neighbouring modules of the same group.
+    pub module_separator: String,
+    /// Triggers for a module arriving within this window of its last run are coalesced.
+    pub min_trigger_interval_ms: u64,
+    /// File trigger events within this window are handled as one batch.
+    pub trigger_debounce_ms: u64,
+    /// Capacity of the queue between monitors and the bar writer. Monitors wait when it is full.
+    pub update_queue_capacity: usize,
+    /// Queued updates older than this are dropped and their module is rerun instead.
+    pub max_update_age_ms: u64,
+    /// Updates arriving within this window of the first one are written to the sinks together.
+    pub min_update_interval_ms: u64,
+    /// At startup, the first write waits up to this long for every module's first result.
+    pub startup_grace_ms: u64,
+    /// Text shown for a module until its first result, unless it sets its own `placeholder`.
+    /// Defaults to the module ID; `""` shows nothing.
+    pub placeholder: Option<String>,
+    /// Consecutive failures after which a module's last value is shown with a `⚠` marker.
+    pub error_marker_after: u32,
+    /// Show `<id>: err` for modules that have never succeeded instead of hiding them.
+    pub show_errors: bool,
+    /// Below this battery percentage (while discharging), intervals double and modules with
+    /// `low_power_disable` stop. 0 turns low-power mode off.
+    pub low_power_mode_battery_pct: u8,
+    /// Longest bar, in characters, for text sinks. Lower-priority modules are truncated or dropped
+    /// to fit. Unlimited when unset.
+    pub max_bar_chars: Option<usize>,
+    /// How module colors and fonts are expressed in text output: dwm status2d escapes, or Pango
+    /// markup for bars that render it (i3bar, waybar, Pango-patched dwm).
+    pub bar_protocol: BarProtocol,
+    /// Save module values to `$XDG_CACHE_HOME/dwm-status-bar/state.json` every 5 minutes and on
+    /// exit, and show the recent ones right away after a restart.
+    pub persist_state: bool,
+    /// Rewritten with every module's text as a JSON object whenever the bar updates, for scripts.
+    pub state_file: Option<PathBuf>,
+    /// Unix socket accepting trigger names, one per line. Defaults to
+    /// `$XDG_RUNTIME_DIR/dwm-bar.trigger.sock` (`/tmp/dwm-bar.trigger.sock` without it).
+    pub trigger_socket: Option<PathBuf>,
+    /// Modules that stay visible on a `hide.all` trigger.
+    pub minimal_modules: Vec<String>,
+    /// Per-module settings keyed by module ID, e.g. `[modules.battery]`.
+    pub modules: HashMap<String, ModuleConfig>,
+    pub datetime: DatetimeConfig,
+    pub disk: DiskConfig,
+    pub ram: RamConfig,
+    pub cpu_load: CpuLoadConfig,
+    pub vpn: VpnConfig,
+    pub net: NetConfig,
+    pub volume: VolumeConfig,
+    pub battery: BatteryConfig,
+    pub bluetooth: BluetoothConfig,
+    pub notification: NotificationConfig,
+    pub cpu_temp: TempConfig,
+    pub gpu_temp: TempConfig,
+    pub agenda: AgendaConfig,
+    pub timetrack: TimetrackConfig,
+    pub ticker: TickerConfig,
+    pub ups: UpsConfig,
+    pub weather: WeatherConfig,
+    pub hog: HogConfig,
+    /// Named colors that module `color` settings can refer to, e.g. `critical = "xres:color1"`.
+    pub colors: HashMap<String, String>,
+    /// Where assembled bars are written. Defaults to a single root window sink with every module.
+    pub sinks: Vec<SinkConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            order: None,
+            section_separator: None,
+            groups: Vec::new(),
+            group_separator: " | ".to_string(),
+            module_separator: " · ".to_string(),
+            min_trigger_interval_ms: 500,
+            trigger_debounce_ms: 100,
+            update_queue_capacity: 64,
+            max_update_age_ms: 10_000,
+            min_update_interval_ms: 100,
+            startup_grace_ms: 500,
+            placeholder: None,
+            error_marker_after: 3,
+            show_errors: false,
+            low_power_mode_battery_pct: 10,
+            max_bar_chars: None,
+            bar_protocol: BarProtocol::default(),
+            persist_state: false,
+            state_file: None,
+            trigger_socket: None,
+            minimal_modules: vec!["datetime".to_string(), "battery".to_string()],
+            modules: HashMap::new(),
+            datetime: DatetimeConfig::default(),
+            disk: DiskConfig::default(),
+            ram: RamConfig::default(),
+            cpu_load: CpuLoadConfig::default(),
+            vpn: VpnConfig::default(),
+            net: NetConfig::default(),
+            volume: VolumeConfig::default(),
+            battery: BatteryConfig::default(),
+            bluetooth: BluetoothConfig::default(),
+            notification: NotificationConfig::default(),
+            cpu_temp: TempConfig::default(),
+            gpu_temp: TempConfig::default(),
+            agenda: AgendaConfig::default(),
+            timetrack: TimetrackConfig::default(),
+            ticker: TickerConfig::default(),
+            ups: UpsConfig::default(),
+            weather: WeatherConfig::default(),
+            hog: HogConfig::default(),
+            colors: HashMap::new(),
+            sinks: vec![SinkConfig::default()],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModuleConfig {
+    /// Text shown in the module's slot until its first result arrives. Defaults to the top-level
+    /// `placeholder`.
+    pub placeholder: Option<String>,
+    /// status2d color for the module on root window sinks: a `[colors]` name, `#rrggbb`,
+    /// or `xres:<resource>` with an optional `|#rrggbb` fallback.
+    pub color: Option<String>,
+    /// Pango font description, e.g. `Monospace Bold 10`. Only used with `bar_protocol = "pango"`.
+    pub font: Option<String>,
+    /// `[start, end)` local hour ranges during which the module is shown, e.g. `[[8, 18]]`.
+    /// Ranges may wrap past midnight (`[22, 6]`). Empty means always visible.
+    pub visible_hours: Vec<[u32; 2]>,
+    /// Don't run the module in low-power mode.
+    pub low_power_disable: bool,
+    /// Consecutive failed runs after which the module is disabled until restart. 0 never disables it.
+    pub max_consecutive_failures: u8,
+    /// Shown in the module's slot once it is disabled. Defaults to `<id> err`.
+    pub failure_text: Option<String>,
+    /// External commands run by the module are killed after this long. 0 waits forever.
+    pub command_timeout_ms: u64,
+    /// Longest text, in characters, for the module on text sinks; longer output is cut with `…`.
+    pub max_len: Option<usize>,
+    /// With `max_bar_chars`, lower priorities are truncated or dropped first. Defaults to the
+    /// module's position in the display order, so modules further right are kept longer.
+    pub priority: Option<i32>,
+    /// With `persist_state`, saved values older than this aren't restored. Defaults to three
+    /// times the module's interval.
+    pub persist_max_age_secs: Option<u64>,
+    /// Hide the module while another module's text matches a regex, e.g. `{ bluetooth = "headphone" }`.
+    pub hide_when: HashMap<String, String>,
+    /// Shell command run whenever the module's text changes, with the module ID as `$1` and the
+    /// new text as `$2`.
+    pub on_change: Option<String>,
+    /// Poll less often while the text stays the same: after `backoff_after` unchanged runs the
+    /// interval doubles, up to this many seconds. A change goes back to the normal interval.
+    pub max_backoff_secs: Option<u64>,
+    pub backoff_after: u32,
+}
+
+impl Default for ModuleConfig {
+    fn default() -> Self {
+        ModuleConfig {
+            placeholder: None,
+            color: None,
+            font: None,
+            visible_hours: Vec::new(),
+            low_power_disable: false,
+            max_consecutive_failures: 10,
+            failure_text: None,
+            command_timeout_ms: 5_000,
+            max_len: None,
+            priority: None,
+            persist_max_age_secs: None,
+            hide_when: HashMap::new(),
+            on_change: None,
+            max_backoff_secs: None,
+            backoff_after: 5,
+        }
+    }
+}
+
+impl ModuleConfig {
+    pub fn visible_at(&self, hour: u32) -> bool {
+        self.visible_hours.is_empty()
+            || self.visible_hours.iter().any(|&[start, end]| {
+                if start <= end {
+                    (start..end).contains(&hour)
+                } else {
+                    hour >= start || hour < end
+                }
+            })
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BarProtocol {
+    /// `^c#rrggbb^` color escapes on the root window; other text sinks get plain text.
+    #[default]
+    Status2d,
+    /// `<span>` tags with module text XML-escaped, on every sink.
+    Pango,
+}
+
+/// An output for the assembled bar. `modules` selects and orders a subset of the display order.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum SinkConfig {
+    /// The X root window name, read by dwm.
+    Xroot {
+        #[serde(default)]
+        modules: Option<Vec<String>>,
+    },
+    /// A text file, replaced atomically on every change.
+    File {
+        path: PathBuf,
+        #[serde(default)]
+        modules: Option<Vec<String>>,
+    },
+    /// One plain text line per change on stdout, e.g. for lemonbar or swaybar's plain protocol.
+    Stdout {
+        #[serde(default)]
+        modules: Option<Vec<String>>,
+    },
+    /// The i3bar JSON protocol on stdout for swaybar, one block per module.
+    Swaybar {
+        #[serde(default)]
+        modules: Option<Vec<String>>,
+    },
+}
+
+impl Default for SinkConfig {
+    /// The root window where dwm reads it, or stdout in builds without X11.
+    fn default() -> Self {
+        if cfg!(all(feature = "x11", not(feature = "stdout-only"))) {
+            SinkConfig::Xroot { modules: None }
+        } else {
+            SinkConfig::Stdout { modules: None }
+        }
+    }
+}
+
+impl SinkConfig {
+    pub fn modules(&self) -> Option<&[String]> {
+        match self {
+            SinkConfig::Xroot { modules }
+            | SinkConfig::File { modules, .. }
+            | SinkConfig::Stdout { modules }
+            | SinkConfig::Swaybar { modules } => modules.as_deref(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatetimeConfig {
+    /// A `chrono` strftime format string.
+    pub format: String,
+    /// When set, a second clock is shown under the `datetime2` module ID.
+    pub secondary_format: Option<String>,
+    /// Prefix the primary clock with the ISO week number, e.g. `W23 Fri 07 Jun 14:32`.
+    pub show_week_number: bool,
+    /// Locale for weekday and month names, e.g. `de_DE`. Defaults to `LC_ALL`, `LC_TIME` or `LANG`.
+    pub locale: Option<String>,
+    /// RFC 3339 timestamp to count down to after the primary clock, e.g. `[3d 4h to vacation]`.
+    /// Once it has passed, the time since is shown instead.
+    pub countdown_to: Option<String>,
+    /// Name of the event in the countdown.
+    pub countdown_label: Option<String>,
+    /// Append `(!)` to the primary clock while the system clock isn't NTP-synchronized.
+    pub ntp_check: bool,
+}
+
+impl Default for DatetimeConfig {
+    fn default() -> Self {
+        DatetimeConfig {
+            format: DEFAULT_DATETIME_FORMAT.to_string(),
+            secondary_format: None,
+            show_week_number: false,
+            locale: None,
+            countdown_to: None,
+            countdown_label: None,
+            ntp_check: false,
+        }
+    }
+}
+
+pub const DEFAULT_DATETIME_FORMAT: &str = "%a %d %b %H:%M:%S";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiskConfig {
+    /// One module per entry, so several mount points can be shown side by side.
+    pub instances: Vec<DiskInstance>,
+    /// Append free space, e.g. `disk: 45% (27G free)`.
+    pub show_absolute: bool,
+    /// Append inode usage, e.g. `disk: 45% (inodes 80%)`.
+    pub show_inodes: bool,
+    /// Show the filesystem type before the percentage, e.g. `disk: zfs 45%`.
+    pub show_fs_type: bool,
+    /// Show the SMART health of each instance's disk, e.g. `disk: 45% ✓`, from `smartctl`.
+    pub check_smart: bool,
+    /// Run `smartctl` through `sudo -n`, for a NOPASSWD sudoers rule instead of running as root.
+    pub smart_sudo: bool,
+    /// How often SMART health is checked, independently of the usage polling.
+    pub smart_interval_secs: u64,
+}
+
+impl Default for DiskConfig {
+    fn default() -> Self {
+        DiskConfig {
+            instances: vec![DiskInstance {
+                id: "disk".to_string(),
+                mount: PathBuf::from("/"),
+                label: "disk".to_string(),
+                alert_threshold: default_disk_alert_threshold(),
+                alert_cooldown_secs: default_disk_alert_cooldown(),
+                inode_alert_threshold: default_disk_alert_threshold(),
+            }],
+            show_absolute: false,
+            show_inodes: false,
+            show_fs_type: false,
+            check_smart: false,
+            smart_sudo: false,
+            smart_interval_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DiskInstance {
+    pub id: String,
+    pub mount: PathBuf,
+    #[serde(default = "default_disk_label")]
+    pub label: String,
+    /// Used percentage above which the module is marked with `!` and a notification is sent.
+    #[serde(default = "default_disk_alert_threshold")]
+    pub alert_threshold: f64,
+    /// Minimum number of seconds between notifications for this disk.
+    #[serde(default = "default_disk_alert_cooldown")]
+    pub alert_cooldown_secs: u64,
+    /// Like `alert_threshold`, for the percentage of inodes used. Checked even when inodes aren't
+    /// shown, as running out of them fails writes just like a full disk.
+    #[serde(default = "default_disk_alert_threshold")]
+    pub inode_alert_threshold: f64,
+}
+
+fn default_disk_label() -> String {
+    "disk".to_string()
+}
+
+fn default_disk_alert_threshold() -> f64 {
+    90.0
+}
+
+fn default_disk_alert_cooldown() -> u64 {
+    3600
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RamConfig {
+    pub format: RamFormat,
+    pub usage: RamUsage,
+    /// Append discrete GPU memory, e.g. `ram: 45% | vram: 8.0G/12G`, from `nvidia-smi` or amdgpu.
+    pub show_vram: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CpuLoadConfig {
+    /// Append a sparkline of the last readings, e.g. `cpu: 45% ▁▂▄▇▆▃▂▁▄▇`.
+    pub sparkline: bool,
+    /// Window CPU usage is measured over, in milliseconds, from 200 (sysinfo's minimum) to 2000.
+    pub sample_period_ms: u64,
+}
+
+impl Default for CpuLoadConfig {
+    fn default() -> Self {
+        CpuLoadConfig {
+            sparkline: false,
+            sample_period_ms: 200,
+        }
+    }
+}
+
+/// How the ram module shows usage: `ram: 26%`, `ram: 4.2G / 16G`, or `ram: 4.2G (26%)`.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RamFormat {
+    #[default]
+    Percent,
+    Absolute,
+    Both,
+}
+
+/// What counts as used memory.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RamUsage {
+    /// Memory applications hold, leaving out page cache and other reclaimable memory.
+    #[default]
+    App,
+    /// Everything that isn't free, including page cache.
+    Total,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VpnConfig {
+    /// Show `vpn: split` when the default route doesn't go through the VPN interface.
+    pub check_routing: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetConfig {
+    /// Append the interface's address to the network status, e.g. `wifi: HomeNet 192.168.1.5`.
+    pub show_ip: bool,
+    pub ip_version: IpVersion,
+    /// Interface whose address is shown. Defaults to the one carrying the default route.
+    pub interface: Option<String>,
+    /// Append the Wi-Fi band, e.g. `wifi: HomeNet 85% (5G)`. Needs `iw`.
+    pub show_band: bool,
+    /// Append the external address from api.ipify.org, e.g. `wifi: HomeNet | WAN: 203.0.113.5`.
+    pub show_wan_ip: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum IpVersion {
+    #[default]
+    V4,
+    V6,
+    /// The IPv4 address and a non-link-local IPv6 address.
+    Both,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VolumeConfig {
+    /// ALSA mixer control read with amixer.
+    pub control: String,
+    /// ALSA card number. Defaults to the default card.
+    pub card: Option<u32>,
+    /// Pulse/PipeWire sink to read instead of ALSA, via pactl or wpctl. `default` follows the
+    /// current default sink.
+    pub sink: Option<String>,
+    /// Prefix the volume with the first four characters of the sink's name, e.g. `vol: Topp 75%`.
+    /// Without `sink` this is the sound server's default sink.
+    pub show_sink_name: bool,
+}
+
+impl Default for VolumeConfig {
+    fn default() -> Self {
+        VolumeConfig { control: "Master".to_string(), card: None, sink: None, show_sink_name: false }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BatteryConfig {
+    /// Draw the charge as a bar before the percentage, e.g. `bat: ▓▓▓▓░ 82/80% D`.
+    pub show_bar: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BluetoothConfig {
+    /// Draw the device's battery level as a bar, e.g. `bt: 🎧 Headphones ▓▓▓▓░ 82%`.
+    pub show_battery_bar: bool,
+    /// Connected devices shown; the rest are counted, e.g. `bt: 🎧 Headphones 82% +2`.
+    pub max_devices: usize,
+    /// Below this battery level a device is marked, e.g. `bt: 🎧 Headphones 🪫 15%`, and a desktop
+    /// notification is sent once. 0 turns it off.
+    pub low_battery_pct: u8,
+}
+
+impl Default for BluetoothConfig {
+    fn default() -> Self {
+        BluetoothConfig { show_battery_bar: false, max_devices: 2, low_battery_pct: 20 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationConfig {
+    /// Show the number of dunst history entries since the last `notification_clear` trigger.
+    pub history: bool,
+    /// Also show the summary of the newest unread notification.
+    pub summary: bool,
+    /// Maximum width of that summary in characters.
+    pub summary_width: usize,
+    /// Show the applications with the most unread dunst notifications, e.g. `Email(3) Discord(7)`.
+    pub show_apps: bool,
+    /// How many applications `show_apps` lists.
+    pub max_apps: u8,
+}
+
+impl Default for NotificationConfig {
+    fn default() -> Self {
+        NotificationConfig {
+            history: false,
+            summary: false,
+            summary_width: 20,
+            show_apps: false,
+            max_apps: 3,
+        }
+    }
+}
+
+/// Desktop notification settings for a temperature module.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TempConfig {
+    /// Notify when two consecutive readings exceed this many °C. Defaults to 90 (CPU) / 85 (GPU).
+    pub alert_threshold: Option<f32>,
+    /// Minimum number of seconds between notifications. Defaults to 300.
+    pub alert_cooldown: Option<u64>,
+    /// °C below the threshold a reading must drop to before the alert clears. Defaults to 5.
+    pub alert_hysteresis: Option<f32>,
+    /// GPU only: hide the module once the GPU has been idle this long, per amdgpu's
+    /// `gpu_busy_percent`. Always shown when that isn't available.
+    pub hide_after_idle_secs: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgendaConfig {
+    /// Shell command printing one `<date> <time> <title>` line per upcoming event.
+    /// Defaults to a `khal list` invocation when khal is installed.
+    pub command: Option<String>,
+    /// Minutes either side of an event's start during which it is shown as `now:`.
+    pub lead_minutes: i64,
+    /// Events further ahead than this are not shown.
+    pub horizon_hours: i64,
+}
+
+impl Default for AgendaConfig {
+    fn default() -> Self {
+        AgendaConfig {
+            command: None,
+            lead_minutes: 5,
+            horizon_hours: 12,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimetrackConfig {
+    /// Time tracker to ask for the running timer. Defaults to whichever of timew and watson is installed.
+    pub tool: Option<TimeTool>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimeTool {
+    Timew,
+    Watson,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TickerConfig {
+    pub symbols: Vec<TickerSymbol>,
+    /// Quote currency for CoinGecko symbols.
+    pub vs_currency: String,
+    /// Maximum width of the whole module in characters.
+    pub max_width: usize,
+}
+
+impl Default for TickerConfig {
+    fn default() -> Self {
+        TickerConfig {
+            symbols: Vec::new(),
+            vs_currency: "usd".to_string(),
+            max_width: 60,
+        }
+    }
+}
+
+/// A ticker symbol. Without `url`, `id` is a CoinGecko coin ID (`bitcoin`); with it, the price
+/// (and optionally the 24h change in percent) is read from that JSON endpoint via JSON pointers
+/// (`/data/amount`) or dot paths (`data.amount`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TickerSymbol {
+    pub id: String,
+    pub label: Option<String>,
+    pub url: Option<String>,
+    #[serde(default)]
+    pub price_pointer: String,
+    pub change_pointer: Option<String>,
+    /// Replaces the `<label> <price> <change>` layout, e.g. `₿ ${value}`. `{value}` is the price
+    /// and `{change}` the 24h change (`▲1.3%`, empty when unknown).
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct UpsConfig {
+    /// UPS name as known to upsd. The module is disabled when unset.
+    pub name: Option<String>,
+    pub host: String,
+    pub port: u16,
+}
+
+impl Default for UpsConfig {
+    fn default() -> Self {
+        UpsConfig {
+            name: None,
+            host: "localhost".to_string(),
+            port: 3493,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeatherConfig {
+    /// wttr.in location, e.g. `Berlin` or `~Eiffel Tower`. The module is disabled when unset;
+    /// an empty string lets wttr.in guess from the IP address.
+    pub location: Option<String>,
+    /// How long the last good reading is shown (marked `*`) while fetching fails.
+    pub cache_ttl_secs: u64,
+}
+
+impl Default for WeatherConfig {
+    fn default() -> Self {
+        WeatherConfig {
+            location: None,
+            cache_ttl_secs: 7200,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HogConfig {
+    /// Scanning the process list is expensive, so the module is off unless enabled.
+    pub enabled: bool,
+    /// Process names are cut to this many characters.
+    pub name_width: usize,
+}
+
+impl Default for HogConfig {
+    fn default() -> Self {
+        HogConfig { enabled: false, name_width: 12 }
+    }
+}
+
+impl Config {
+    /// `$XDG_CONFIG_HOME/dwm-status/config.toml`, falling back to `~/.config` when the variable
+    /// isn't set, if that file exists.
+    pub fn default_path() -> Option<PathBuf> {
+        let path = dirs::config_dir()?.join("dwm-status").join("config.toml");
+        path.exists().then_some(path)
+    }
+
+    pub fn load(path: Option<&Path>) -> Result<Config> {
+        let Some(path) = path else {
+            return Ok(Config::default());
+        };
+        let contents = fs::read_to_string(path).with_context(|| format!("Cannot read config file {}", path.display()))?;
+        toml::from_str(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+    }
+
+    pub fn min_trigger_interval(&self) -> Duration {
+        Duration::from_millis(self.min_trigger_interval_ms)
+    }
+
+    pub fn trigger_debounce(&self) -> Duration {
+        Duration::from_millis(self.trigger_debounce_ms)
+    }
+
+    pub fn max_update_age(&self) -> Duration {
+        Duration::from_millis(self.max_update_age_ms)
+    }
+
+    pub fn min_update_interval(&self) -> Duration {
+        Duration::from_millis(self.min_update_interval_ms)
+    }
+
+    pub fn startup_grace(&self) -> Duration {
+        Duration::from_millis(self.startup_grace_ms)
+    }
+
+    pub fn module(&self, id: &str) -> ModuleConfig {
+        self.modules.get(id).cloned().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(toml: &str) -> Result<Config, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    #[test]
+    fn empty_file_is_the_default_config() {
+        let config = parse("").unwrap();
+        let default = Config::default();
+        assert_eq!(config.group_separator, default.group_separator);
+        assert_eq!(config.max_update_age_ms, default.max_update_age_ms);
+        assert_eq!(config.minimal_modules, default.minimal_modules);
+        assert_eq!(config.datetime.format, DEFAULT_DATETIME_FORMAT);
+        assert_eq!(config.sinks.len(), 1);
+    }
+
+    #[test]
+    fn readme_example_parses() {
+        let readme = include_str!("../README.md");
+        let start = readme.find("```toml\n").unwrap() + "```toml\n".len();
+        let end = start + readme[start..].find("```").unwrap();
+        if let Err(e) = parse(&readme[start..end]) {
+            panic!("README config example is invalid: {}", e);
+        }
+    }
+
+    #[test]
+    fn typos_are_rejected() {
+        assert!(parse("group_seperator = \" \"").is_err());
+        assert!(parse("[datetime]\nfromat = \"%H:%M\"").is_err());
+        assert!(parse("[modules.battery]\nprioirty = 1").is_err());
+    }
+
+    #[test]
+    fn partial_tables_fill_their_defaults() {
+        let config = parse("[datetime]\nshow_week_number = true\n\n[modules.battery]\npriority = 5").unwrap();
+        assert!(config.datetime.show_week_number);
+        assert_eq!(config.datetime.format, DEFAULT_DATETIME_FORMAT);
+        let battery = config.module("battery");
+        assert_eq!(battery.priority, Some(5));
+        assert_eq!(battery.max_consecutive_failures, ModuleConfig::default().max_consecutive_failures);
+        assert_eq!(battery.command_timeout_ms, 5_000);
+        // Modules without a table get every default.
+        assert_eq!(config.module("ram").priority, None);
+    }
+}
@@ -0,0 +1,133 @@
+use crate::command::CommandError;
+use crate::registry::ModuleId;
+use std::collections::HashMap;
+
+/// Appended to a module's last good value once it has failed too many times in a row.
+pub const ERROR_MARKER: &str = "⚠";
+
+/// The result of one module run, as sent to the bar writer.
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    /// Shown until the module's first result arrives; does not count as a success.
+    Placeholder(String),
+    Success(String),
+    /// `command` is set when the failure came from an external command.
+    Failure { message: String, command: Option<CommandError> },
+    /// The module was disabled for good (too many failures or panics); shows the given text
+    /// regardless of `show_errors`.
+    Stopped(String),
+}
+
+#[derive(Debug, Default)]
+struct Health {
+    last_ok: Option<String>,
+    failures: u32,
+}
+
+/// Turns module outcomes into the text shown in the bar, tracking consecutive failures.
+#[derive(Debug)]
+pub struct FailureTracker {
+    /// Consecutive failures after which the marker is shown.
+    threshold: u32,
+    /// Show `<id>: err` for modules that have never succeeded instead of hiding them.
+    show_errors: bool,
+    modules: HashMap<ModuleId, Health>,
+}
+
+impl FailureTracker {
+    pub fn new(threshold: u32, show_errors: bool) -> Self {
+        FailureTracker { threshold, show_errors, modules: HashMap::new() }
+    }
+
+    /// Seeds a module with a value persisted by a previous run. It replaces the placeholder and
+    /// is kept, like any earlier success, while the module fails.
+    pub fn restore(&mut self, id: &ModuleId, text: String) {
+        self.modules.entry(id.clone()).or_default().last_ok = Some(text);
+    }
+
+    pub fn apply(&mut self, id: &ModuleId, outcome: Outcome) -> String {
+        let health = self.modules.entry(id.clone()).or_default();
+        match outcome {
+            Outcome::Placeholder(text) => health.last_ok.clone().unwrap_or(text),
+            Outcome::Success(text) => {
+                health.failures = 0;
+                health.last_ok = Some(text.clone());
+                text
+            }
+            // Always shown, even for modules that never worked: a disabled module won't come back
+            // until restart, so hiding it would look like it simply has nothing to say.
+            Outcome::Stopped(text) => text,
+            Outcome::Failure { message, command } => {
+                health.failures += 1;
+                if health.failures == self.threshold {
+                    let code = command.as_ref().and_then(CommandError::exit_code);
+                    tracing::warn!(target: "monitor", exit_code = ?code, "Module '{}' failed {} times in a row, last error: {}", id, health.failures, message);
+                }
+                // A missing command won't come back by itself, so don't wait for more failures.
+                let permanent = command.as_ref().is_some_and(CommandError::is_not_found);
+                match &health.last_ok {
+                    None if self.show_errors => format!("{}: err", id),
+                    None => String::new(),
+                    Some(text) if (permanent || health.failures >= self.threshold) && !text.is_empty() => format!("{} {}", text, ERROR_MARKER),
+                    Some(text) => text.clone(),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bar::{Bar, Markup, Separators};
+    use crate::config::Config;
+    use crate::registry::Section;
+
+    /// The bar as the root window sink renders it, with `cpu_load` before `datetime`.
+    fn assemble(results: &HashMap<ModuleId, String>) -> String {
+        let order = [(ModuleId::from("cpu_load"), Section::Center), (ModuleId::from("datetime"), Section::Center)];
+        let separators = Separators::new(&Config::default());
+        Bar { order: &order, results, markup: Markup::Plain, budget: None, overlay: None, separators: &separators }.assemble()
+    }
+
+    fn failure() -> Outcome {
+        Outcome::Failure { message: "boom".to_string(), command: None }
+    }
+
+    #[test]
+    fn marker_after_threshold_clears_on_success() {
+        let mut tracker = FailureTracker::new(3, false);
+        let id = ModuleId::from("cpu_load");
+        let steps = [
+            (Outcome::Success("cpu: 5%".to_string()), 0, " cpu: 5% | 12:00 "),
+            (failure(), 1, " cpu: 5% | 12:00 "),
+            (failure(), 2, " cpu: 5% | 12:00 "),
+            (failure(), 3, " cpu: 5% ⚠ | 12:00 "),
+            (Outcome::Success("cpu: 7%".to_string()), 0, " cpu: 7% | 12:00 "),
+            // The count starts over: one failure is not enough for the marker again.
+            (failure(), 1, " cpu: 7% | 12:00 "),
+        ];
+        let mut results = HashMap::from([(ModuleId::from("datetime"), "12:00".to_string())]);
+        for (outcome, failures, bar) in steps {
+            results.insert(id.clone(), tracker.apply(&id, outcome));
+            assert_eq!(tracker.modules[&id].failures, failures);
+            assert_eq!(assemble(&results), bar);
+        }
+    }
+
+    #[test]
+    fn never_succeeded_is_hidden_unless_show_errors() {
+        let id = ModuleId::from("vpn");
+        assert_eq!(FailureTracker::new(3, false).apply(&id, failure()), "");
+        assert_eq!(FailureTracker::new(3, true).apply(&id, failure()), "vpn: err");
+    }
+
+    #[test]
+    fn missing_command_is_marked_right_away() {
+        let mut tracker = FailureTracker::new(3, false);
+        let id = ModuleId::from("volume");
+        tracker.apply(&id, Outcome::Success("vol: 40%".to_string()));
+        let command = Some(CommandError::Spawn { cmd: "pactl".to_string(), kind: std::io::ErrorKind::NotFound });
+        assert_eq!(tracker.apply(&id, Outcome::Failure { message: "gone".to_string(), command }), "vol: 40% ⚠");
+    }
+}
@@ -5,37 +5,98 @@ How to add a new module to the status bar:
     - Create a new `async fn your_monitor_name() -> Result<String>`.
     - This function should perform the check and return a `Result` containing the formatted string to display.
     - For performance, use `tokio::process::Command` for external commands instead of `std::process::Command`.
-    - See `battery_monitor` or `volume_monitor` for examples.
+    - See `battery_monitor` or `network_monitor` for examples.
 
-2.  Add the module to `MODULE_ORDER`:
-    - Add a unique string ID for your module to the `MODULE_ORDER` constant array. The order in this array determines the display order in the bar.
-    - Example: `const MODULE_ORDER: &[&str] = &["..., "your_module_id"];`
+2.  Add the module to `DEFAULT_MODULE_ORDER`:
+    - Add a unique string ID for your module to the `DEFAULT_MODULE_ORDER` constant array. The order in this array determines the default display order in the bar.
+    - Example: `const DEFAULT_MODULE_ORDER: &[&str] = &["..., "your_module_id"];`
+    - Users can override the order with the `order` config key; modules missing from it are not shown.
 
-3.  Spawn the monitor in `main`:
-    - In the `main` function, add a `spawn_monitor` call for your new module.
-    - Provide the ID, a `Duration` for the update interval, the function name, and the channels.
+3.  Register and spawn the monitor:
+    - Add a `ModuleInfo` entry to `detect_modules` with the interval and the `Dependency` list that enables it,
+      e.g. `ModuleInfo::new("your_module_id", 10, &[Dependency::Command("tool")])`. Any one dependency is enough.
+    - In the `main` function, add a `spawn_monitor` call guarded by `enabled.get("your_module_id")`.
+    - Provide the ID, the detected interval, the function name, and the shared `MonitorContext`.
 
 4.  (Optional) Add a manual trigger:
     - If you want to be able to manually trigger an update (e.g., via a script or keybinding), your monitor will automatically support it.
     - Simply create an empty file in `/tmp/dwm-bar-triggers/` with the same name as your module ID.
 */
+mod agenda;
+mod bar;
+mod bar_state;
+#[cfg(feature = "benchmark")]
+mod benchmark;
+mod bluetooth;
+mod brightness;
+mod budget;
+mod cadence;
+mod check;
+mod clock;
+mod colors;
+mod command;
+mod config;
+mod health;
+mod metrics;
+mod net;
+#[cfg(target_os = "linux")]
+mod netlink;
+mod notification;
+mod overlay;
+mod pango;
+#[cfg(target_os = "linux")]
+mod power;
+mod registry;
+mod sink;
+mod smart;
+mod state;
+mod stats;
+mod supervisor;
+mod system;
+mod ups;
+mod weather;
+mod ticker;
+mod timetrack;
+mod util;
+mod visibility;
+mod volume;
+mod vram;
+#[cfg(feature = "x11")]
+mod xroot;
+
 use anyhow::Result;
+use bar::Update;
+use bar_state::BarState;
 use clap::Parser;
-use regex::Regex;
-use std::collections::HashMap;
+use command::{run_command, run_command_clean};
+use config::{Config, RamFormat, RamUsage, SinkConfig};
+use health::{FailureTracker, Outcome};
+use registry::{ModuleId, ModuleRegistry};
+use stats::Stats;
+use supervisor::Supervisor;
+use sink::Sink;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::env;
-use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
-use sysinfo::{CpuExt, DiskExt, System, SystemExt};
+use system::SystemHandle;
+use visibility::{HideRules, Visibility};
 use tokio::sync::{broadcast, mpsc};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
-const MODULE_ORDER: &[&str] = &[
-   "vpn", "notification", "cpu_load", "ram", "disk", "cpu_temp", "gpu_temp", "battery", "volume", "bluetooth", "net", "datetime",
+const DEFAULT_MODULE_ORDER: &[&str] = &[
+   "privacy", "vpn", "notification", "idle", "cpu_load", "ram", "hog", "disk", "cpu_temp", "gpu_temp", "battery", "ups", "volume", "brightness", "ext_brightness", "bluetooth", "net", "agenda", "timetrack", "ticker", "weather", "datetime", "datetime2",
 ];
 const TRIGGER_DIR: &str = "/tmp/dwm-bar-triggers";
+const NETWORK_SCRIPT: &str = "/home/sky/nix-config/bash/network-status.sh";
+/// Reserved trigger name that refreshes every module.
+const TRIGGER_ALL: &str = "all";
+/// On SIGTERM or SIGINT, monitors still running after this are aborted.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -43,12 +104,109 @@ struct Args {
     /// Enable profiling mode to measure module execution time.
     #[arg(short, long)]
     profile: bool,
+
+    /// Path to a TOML config file. Defaults to `$XDG_CONFIG_HOME/dwm-status/config.toml` if it exists.
+    #[arg(short, long)]
+    config: Option<PathBuf>,
+
+    /// Also write logs to this file, rotated daily (a date suffix is appended).
+    #[arg(long)]
+    log_file: Option<PathBuf>,
+
+    /// Number of rotated log files to keep.
+    #[arg(long, default_value_t = 7)]
+    log_max_files: usize,
+
+    /// Serve Prometheus metrics for module values and execution times on this localhost port.
+    #[arg(long)]
+    metrics_port: Option<u16>,
+
+    /// Where to write the bar; repeat for several outputs. Overrides the `[[sinks]]` config.
+    #[arg(long, value_enum)]
+    output: Vec<OutputKind>,
+
+    /// Target file for `--output file`.
+    #[arg(long)]
+    file_path: Option<PathBuf>,
+
+    /// Override a module's interval in seconds, e.g. `--interval battery=10` or `cpu_load=0.5`.
+    /// 0 runs the module once at startup and then only on triggers. Repeatable.
+    #[arg(long, value_name = "ID=SECONDS", value_parser = parse_interval)]
+    interval: Vec<(String, Duration)>,
+
+    /// Only run modules whose ID matches this regex, e.g. `'^(battery|volume)$'`. The others keep
+    /// their place in the order but stay empty.
+    #[arg(long, value_name = "REGEX", value_parser = parse_module_filter)]
+    module_filter: Option<regex::Regex>,
+
+    /// Print every known module, whether it would run on this system and its interval, then exit.
+    #[arg(long)]
+    list_modules: bool,
+
+    /// Validate the config file (module IDs, ranges, colors, formats), print every problem with
+    /// its key and exit with status 1 if there are any.
+    #[arg(long)]
+    check_config: bool,
+
+    /// Run every enabled module once, print a table of results and exit without writing the bar.
+    /// Exits with an error if any module fails or doesn't report in time.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Run every enabled module once, then time rendering the bar and writing it to the root
+    /// window from those results, and print the throughput.
+    #[cfg(feature = "benchmark")]
+    #[arg(long)]
+    benchmark: bool,
+
+    /// Log filter in `RUST_LOG` syntax, e.g. `info,monitor::battery=debug`. Overrides `RUST_LOG`.
+    #[arg(long)]
+    log_level: Option<String>,
 }
 
-#[derive(Debug, Clone)]
-struct Update {
-    id: &'static str,
-    value: String,
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum OutputKind {
+    Xroot,
+    File,
+    Stdout,
+    Swaybar,
+}
+
+/// The sinks selected with `--output`, each showing every module.
+fn cli_sinks(args: &Args) -> Result<Vec<SinkConfig>> {
+    args.output
+        .iter()
+        .map(|kind| {
+            Ok(match kind {
+                OutputKind::Xroot => SinkConfig::Xroot { modules: None },
+                OutputKind::File => {
+                    let path = args.file_path.clone().ok_or_else(|| anyhow::anyhow!("--output file needs --file-path"))?;
+                    SinkConfig::File { path, modules: None }
+                }
+                OutputKind::Stdout => SinkConfig::Stdout { modules: None },
+                OutputKind::Swaybar => SinkConfig::Swaybar { modules: None },
+            })
+        })
+        .collect()
+}
+
+/// Shared handles every monitor task needs besides its own ID, interval and function.
+#[derive(Clone)]
+struct MonitorContext {
+    update_tx: mpsc::Sender<Update>,
+    trigger_tx: broadcast::Sender<ModuleId>,
+    registry: Arc<ModuleRegistry>,
+    config: Arc<Config>,
+    stats: Stats,
+    supervisor: Supervisor,
+    /// Set while the battery is below `low_power_mode_battery_pct`: intervals double and modules
+    /// with `low_power_disable` stop running.
+    low_power: Arc<AtomicBool>,
+    profile: bool,
+    /// Modules run once and stop, for `--dry-run`.
+    dry_run: bool,
+    /// Cancelled on SIGTERM or SIGINT; monitors stop after their current run.
+    shutdown: CancellationToken,
 }
 
 fn command_exists(cmd: &str) -> bool {
@@ -62,269 +220,2178 @@ fn command_exists(cmd: &str) -> bool {
     false
 }
 
+/// Sets up stderr logging plus the optional rotating log file. The returned guard must be
+/// kept alive for the file writer to flush.
+fn init_logging(args: &Args) -> Result<Option<tracing_appender::non_blocking::WorkerGuard>> {
+    use anyhow::Context;
+    use tracing_appender::rolling::{RollingFileAppender, Rotation};
+    use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+    let filter = match &args.log_level {
+        Some(level) => EnvFilter::try_new(level).with_context(|| format!("Invalid log level '{}'", level))?,
+        None => EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")),
+    };
+
+    let (file_layer, guard) = match &args.log_file {
+        Some(path) => {
+            let dir = path.parent().filter(|d| !d.as_os_str().is_empty()).unwrap_or(Path::new("."));
+            let prefix = path.file_name().and_then(|n| n.to_str()).context("Log file path has no file name")?;
+            fs::create_dir_all(dir).with_context(|| format!("Cannot create log directory {}", dir.display()))?;
+            let appender = RollingFileAppender::builder()
+                .rotation(Rotation::DAILY)
+                .filename_prefix(prefix)
+                .max_log_files(args.log_max_files)
+                .build(dir)
+                .with_context(|| format!("Cannot open log file {}", path.display()))?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(fmt::layer().with_writer(writer).with_ansi(false)), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .init();
+    Ok(guard)
+}
+
 #[tokio::main]
-async fn main() {
+async fn main() -> Result<()> {
     let args = Args::parse();
-    tracing_subscriber::fmt::init();
+    let _log_guard = init_logging(&args)?;
+    let config_path = args.config.clone().or_else(Config::default_path);
+    match &config_path {
+        Some(path) => tracing::info!("Loading config from {}", path.display()),
+        None => tracing::info!("No config file, using the defaults"),
+    }
+    let config = Arc::new(Config::load(config_path.as_deref())?);
+    let gpu_zones = gpu_thermal_zones();
+    let mut detected = detect_modules(&config, &gpu_zones);
+    apply_interval_overrides(&mut detected, &args.interval)?;
+    if args.list_modules {
+        print_modules(&detected);
+        return Ok(());
+    }
+    if args.check_config {
+        let known: Vec<&str> = detected.iter().map(|m| m.id.as_str()).collect();
+        let errors = check::check(&config, &known);
+        let source = config_path.as_deref().map_or("default config".to_string(), |p| p.display().to_string());
+        if errors.is_empty() {
+            println!("{}: OK", source);
+            return Ok(());
+        }
+        for error in &errors {
+            eprintln!("{}: {}", source, error);
+        }
+        std::process::exit(1);
+    }
+    if let Some(filter) = &args.module_filter {
+        detected.retain(|m| filter.is_match(&m.id));
+        if detected.is_empty() {
+            anyhow::bail!("--module-filter '{}' matches no module", filter);
+        }
+    }
+    let registry = Arc::new(build_registry(&config, &gpu_zones));
+    let hide_rules = HideRules::new(&config, &registry)?;
     fs::create_dir_all(TRIGGER_DIR).expect("Cannot create trigger directory");
+    let sink_configs = if args.output.is_empty() { config.sinks.clone() } else { cli_sinks(&args)? };
+    let budget = budget::Budget::new(&config, &registry);
+    let pango = pango::Pango::new(&config, &registry);
+    let separators = bar::Separators::new(&config);
+    // Both run every module once and exit instead of writing the bar.
+    #[cfg(feature = "benchmark")]
+    let run_once = args.dry_run || args.benchmark;
+    #[cfg(not(feature = "benchmark"))]
+    let run_once = args.dry_run;
+    let mut sinks = if run_once {
+        Vec::new()
+    } else {
+        sink_configs
+            .iter()
+            .map(|sink| Sink::new(sink, &registry, budget.clone(), pango.clone(), separators.clone()))
+            .collect::<Result<Vec<_>>>()?
+    };
 
-    let (update_tx, mut update_rx) = mpsc::channel::<Update>(32);
-    let (trigger_tx, _) = broadcast::channel::<&'static str>(16);
-    let results = Arc::new(Mutex::new(HashMap::new()));
-    let sys = Arc::new(Mutex::new(System::new_all()));
+    // Bounded on purpose: an unbounded queue never makes a monitor wait, but if the writer stalls
+    // (a hung X connection, a slow filesystem) it grows without limit and every value in it is
+    // outdated by the time it is applied. With a bound, monitors block in `send` once the queue is
+    // full, which simply delays their next run; nothing is lost because each module's latest value
+    // overwrites the previous one anyway. The capacity is configurable for setups with many modules.
+    let (update_tx, mut update_rx) = mpsc::channel::<Update>(config.update_queue_capacity.max(1));
+    // Sized so a refresh-all fan-out fits several times over without lagging receivers.
+    let (trigger_tx, _) = broadcast::channel::<ModuleId>(registry.order().len().max(4) * 4);
+    let results = Arc::new(Mutex::new(BarState::default()));
+    let stats = Stats::default();
+    let sys = SystemHandle::spawn(stats.clone(), args.profile, Duration::from_millis(config.cpu_load.sample_period_ms))?;
+    let colors: colors::ModuleColors = Arc::new(Mutex::new(colors::resolve(&config, &registry).await));
+    let shutdown = CancellationToken::new();
+    let supervisor = Supervisor::start(update_tx.clone(), shutdown.clone());
+    let ctx = MonitorContext {
+        update_tx,
+        trigger_tx: trigger_tx.clone(),
+        registry: registry.clone(),
+        config,
+        stats: stats.clone(),
+        supervisor,
+        low_power: Arc::new(AtomicBool::new(false)),
+        profile: args.profile,
+        dry_run: run_once,
+        shutdown,
+    };
 
-    let trigger_sub = || trigger_tx.subscribe();
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(15))
+        .user_agent(concat!("dwm-status-bar-rs/", env!("CARGO_PKG_VERSION")))
+        .build()?;
 
-    // --- Core modules (always enabled) ---
-    spawn_monitor("datetime", Duration::from_secs(1), datetime_monitor, update_tx.clone(), trigger_sub(), args.profile);
-    let sys_clone = sys.clone();
-    spawn_monitor("disk", Duration::from_secs(30), move || disk_monitor(sys_clone.clone()), update_tx.clone(), trigger_sub(), args.profile);
-    let sys_clone = sys.clone();
-    spawn_monitor("ram", Duration::from_secs(5), move || ram_monitor(sys_clone.clone()), update_tx.clone(), trigger_sub(), args.profile);
-    spawn_monitor("cpu_load", Duration::from_secs(2), cpu_load_monitor, update_tx.clone(), trigger_sub(), args.profile);
-    spawn_monitor("vpn", Duration::from_secs(10), vpn_monitor, update_tx.clone(), trigger_sub(), args.profile);
+    let enabled: HashMap<&str, Duration> = detected.iter().filter(|m| m.enabled).map(|m| (m.id.as_str(), m.interval)).collect();
+    let intervals: Vec<String> = detected.iter().filter(|m| m.enabled).map(|m| format!("{}={}", m.id, format_interval(m.interval))).collect();
+    tracing::info!("Module intervals: {}", intervals.join(" "));
 
-    // --- Conditional modules (check for dependencies) ---
-    if Path::new("/sys/class/thermal/thermal_zone0/temp").exists() {
-        spawn_monitor("cpu_temp", Duration::from_secs(10), cpu_temp_monitor, update_tx.clone(), trigger_sub(), args.profile);
+    let locale = datetime_locale(ctx.config.datetime.locale.as_deref());
+    if let Some(&interval) = enabled.get("datetime") {
+        let mut format = checked_datetime_format(&ctx.config.datetime.format);
+        if ctx.config.datetime.show_week_number {
+            format.insert_str(0, "W%V ");
+        }
+        let format: Arc<str> = format.into();
+        let countdown = ctx.config.datetime.countdown_to.as_deref().and_then(|target| Countdown::new(target, ctx.config.datetime.countdown_label.clone()));
+        let unsynced = ctx.config.datetime.ntp_check.then(|| Arc::new(AtomicBool::new(false)));
+        #[cfg(target_os = "linux")]
+        if let Some(unsynced) = &unsynced {
+            tokio::spawn(clock::watch_ntp(unsynced.clone()));
+        }
+        spawn_monitor("datetime", interval, move || datetime_monitor(format.clone(), locale, countdown.clone(), unsynced.clone()), &ctx);
+    }
+    if let (Some(&interval), Some(secondary)) = (enabled.get("datetime2"), &ctx.config.datetime.secondary_format) {
+        let format: Arc<str> = checked_datetime_format(secondary).into();
+        spawn_monitor("datetime2", interval, move || datetime_monitor(format.clone(), locale, None, None), &ctx);
+    }
+    let has_notify_send = command_exists("notify-send");
+    let check_smart = ctx.config.disk.check_smart && command_exists("smartctl");
+    if ctx.config.disk.check_smart && !check_smart {
+        tracing::warn!(target: "monitor", "disk.check_smart is set, but smartctl is not installed");
+    }
+    let disk_config = Arc::new(ctx.config.disk.clone());
+    for disk in &ctx.config.disk.instances {
+        let Some(&interval) = enabled.get(disk.id.as_str()) else { continue };
+        let (sys, mount, label, config) = (sys.clone(), disk.mount.clone(), disk.label.clone(), disk_config.clone());
+        let smart = if check_smart { spawn_smart_poll(disk, &ctx) } else { None };
+        let cooldown = Duration::from_secs(disk.alert_cooldown_secs);
+        let alerts = Arc::new(Mutex::new(DiskAlerts {
+            space: DiskAlert::new(disk.alert_threshold, cooldown, has_notify_send),
+            inodes: DiskAlert::new(disk.inode_alert_threshold, cooldown, has_notify_send),
+        }));
+        spawn_monitor(&disk.id, interval, move || {
+            disk_monitor(sys.clone(), mount.clone(), label.clone(), config.clone(), alerts.clone(), smart.clone())
+        }, &ctx);
+    }
+    if let Some(&interval) = enabled.get("ram") {
+        let (sys, format, usage) = (sys.clone(), ctx.config.ram.format, ctx.config.ram.usage);
+        let vram = ctx.config.ram.show_vram.then(vram::Vram::detect).flatten().map(Arc::new);
+        match &vram {
+            Some(vram) => tracing::info!(target: "monitor::ram", "Reading VRAM usage via {:?}", vram),
+            None if ctx.config.ram.show_vram => tracing::info!(target: "monitor::ram", "No nvidia-smi or amdgpu card found, not showing VRAM"),
+            None => {}
+        }
+        spawn_monitor("ram", interval, move || ram_monitor(sys.clone(), format, usage, vram.clone()), &ctx);
+    }
+    if let Some(&interval) = enabled.get("hog") {
+        let (sys, name_width) = (sys.clone(), ctx.config.hog.name_width);
+        spawn_monitor("hog", interval, move || hog_monitor(sys.clone(), name_width), &ctx);
+    }
+    if let Some(&interval) = enabled.get("cpu_load") {
+        let sys = sys.clone();
+        let history = ctx.config.cpu_load.sparkline.then(|| Arc::new(Mutex::new(VecDeque::with_capacity(CPU_SPARKLINE_LEN))));
+        spawn_monitor("cpu_load", interval, move || cpu_load_monitor(sys.clone(), history.clone()), &ctx);
+    }
+    #[cfg(target_os = "linux")]
+    if let Some(&interval) = enabled.get("vpn") {
+        let (check_routing, mullvad) = (ctx.config.vpn.check_routing, command_exists("mullvad"));
+        spawn_monitor("vpn", interval, move || vpn_monitor(check_routing, mullvad), &ctx);
+    }
+    #[cfg(target_os = "linux")]
+    if let Some(&interval) = enabled.get("cpu_temp") {
+        let alert = Arc::new(Mutex::new(TempAlert::new("CPU".to_string(), &ctx.config.cpu_temp, 90.0, has_notify_send)));
+        // Throttling before the bar started isn't news.
+        let throttles = Arc::new(std::sync::atomic::AtomicU64::new(throttle_count().unwrap_or(0)));
+        spawn_monitor("cpu_temp", interval, move || cpu_temp_monitor(alert.clone(), throttles.clone()), &ctx);
+    }
+    #[cfg(target_os = "linux")]
+    for (id, path) in &gpu_zones {
+        let Some(&interval) = enabled.get(id.as_str()) else { continue };
+        // `gpu_temp_1` is labelled `gpu1`, a lone `gpu_temp` just `gpu`.
+        let prefix = format!("gpu{}", id.trim_start_matches("gpu_temp").trim_start_matches('_'));
+        let alert = Arc::new(Mutex::new(TempAlert::new(prefix.to_uppercase(), &ctx.config.gpu_temp, 85.0, has_notify_send)));
+        let path = path.clone();
+        let condition = ctx.config.gpu_temp.hide_after_idle_secs.map(|secs| gpu_busy_condition(Duration::from_secs(secs)));
+        spawn_monitor_with(id, interval, condition, on_change_command(&ctx.config, id), move || {
+            let (path, prefix, alert) = (path.clone(), prefix.clone(), alert.clone());
+            async move { temp_monitor(&path, &prefix, alert).await }
+        }, &ctx);
+    }
+    if let Some(&interval) = enabled.get("net") {
+        let net = Arc::new(ctx.config.net.clone());
+        let wan_ip = net.show_wan_ip.then(net::WanIp::default);
+        if let (Some(wan_ip), Some(id)) = (&wan_ip, ctx.registry.get("net")) {
+            tokio::spawn(net::poll_wan_ip(http.clone(), wan_ip.clone(), id, ctx.trigger_tx.clone()));
+        }
+        spawn_monitor("net", interval, move || network_monitor(net.clone(), wan_ip.clone()), &ctx);
+    }
+    #[cfg(target_os = "linux")]
+    if let Some(&interval) = enabled.get("battery") {
+        tokio::spawn(battery_hotplug(ctx.clone(), interval));
+    }
+    if let (Some(&interval), Some(name)) = (enabled.get("ups"), ctx.config.ups.name.clone()) {
+        let ups = Arc::new(ups::Ups::new(ctx.config.ups.clone(), name));
+        let poll_ups = ups.clone();
+        spawn_monitor("ups", interval, move || {
+            let ups = poll_ups.clone();
+            async move { ups::monitor(&ups).await }
+        }, &ctx);
+        // Refresh every 10s instead of every minute while running on battery.
+        if let Some(id) = ctx.registry.get("ups") {
+            let trigger_tx = ctx.trigger_tx.clone();
+            tokio::spawn(async move {
+                let mut interval = tokio::time::interval(Duration::from_secs(10));
+                loop {
+                    interval.tick().await;
+                    if ups.on_battery.load(std::sync::atomic::Ordering::Relaxed) {
+                        let _ = trigger_tx.send(id.clone());
+                    }
+                }
+            });
+        }
     }
-    if Path::new("/sys/class/thermal/thermal_zone1/temp").exists() {
-        spawn_monitor("gpu_temp", Duration::from_secs(30), gpu_temp_monitor, update_tx.clone(), trigger_sub(), args.profile);
+    if let Some(&interval) = enabled.get("volume") {
+        let volume = Arc::new(volume::Volume::new(ctx.config.volume.clone()));
+        spawn_monitor("volume", interval, move || {
+            let volume = volume.clone();
+            async move { volume::monitor(&volume).await }
+        }, &ctx);
+        if let Some(id) = ctx.registry.get("volume") {
+            let trigger_tx = ctx.trigger_tx.clone();
+            tokio::spawn(async move {
+                match volume::watch(id, trigger_tx).await {
+                    Ok(()) => tracing::warn!(target: "monitor::volume", "PulseAudio connection closed, polling only"),
+                    Err(e) => tracing::debug!(target: "monitor::volume", "Not watching PulseAudio volume events, polling only: {}", e),
+                }
+            });
+        }
+        #[cfg(target_os = "linux")]
+        if let (None, Some(id)) = (&ctx.config.volume.sink, ctx.registry.get("volume")) {
+            // amixer's default device is the first card.
+            let (card, trigger_tx) = (ctx.config.volume.card.unwrap_or(0), ctx.trigger_tx.clone());
+            std::thread::Builder::new().name("alsa-events".into()).spawn(move || match volume::watch_alsa(card, id, trigger_tx) {
+                Ok(()) => tracing::warn!(target: "monitor::volume", "ALSA control device closed, polling only"),
+                Err(e) => tracing::debug!(target: "monitor::volume", "Not watching ALSA mixer events, polling only: {:#}", e),
+            })?;
+        }
     }
-    if Path::new("/home/sky/nix-config/bash/network-status.sh").exists() {
-        spawn_monitor("net", Duration::from_secs(10), network_monitor, update_tx.clone(), trigger_sub(), args.profile);
+    if let (Some(&interval), Some(device)) = (enabled.get("brightness"), brightness::backlight()) {
+        spawn_monitor("brightness", interval, move || {
+            let device = device.clone();
+            async move { brightness::monitor(&device).await }
+        }, &ctx);
     }
-    if command_exists("acpi") {
-        spawn_monitor("battery", Duration::from_secs(30), battery_monitor, update_tx.clone(), trigger_sub(), args.profile);
+    if let Some(&interval) = enabled.get("ext_brightness") {
+        spawn_monitor("ext_brightness", interval, brightness::external_monitor, &ctx);
     }
-    if command_exists("amixer") {
-        spawn_monitor("volume", Duration::from_secs(10), volume_monitor, update_tx.clone(), trigger_sub(), args.profile);
+    if let Some(&interval) = enabled.get("bluetooth") {
+        match bluetooth::Bluetooth::connect().await {
+            Ok(bt) => {
+                let bt = Arc::new(bt);
+                if let Some(id) = ctx.registry.get("bluetooth") {
+                    let (bt, trigger_tx) = (bt.clone(), ctx.trigger_tx.clone());
+                    tokio::spawn(async move {
+                        if let Err(e) = bt.watch_connections(id, trigger_tx).await {
+                            tracing::warn!(target: "monitor::bluetooth", "Stopped watching for connection changes, polling only: {}", e);
+                        }
+                    });
+                }
+                let config = Arc::new(ctx.config.bluetooth.clone());
+                spawn_monitor("bluetooth", interval, move || {
+                    let (bt, config) = (bt.clone(), config.clone());
+                    async move { bluetooth::monitor(&bt, &config, has_notify_send).await }
+                }, &ctx);
+            }
+            Err(e) => tracing::warn!("Bluetooth disabled, cannot connect to the system bus: {}", e),
+        }
     }
-    if command_exists("bluetoothctl") {
-        spawn_monitor("bluetooth", Duration::from_secs(60), bluetooth_monitor, update_tx.clone(), trigger_sub(), args.profile);
+    if let (Some(&interval), Some(daemon)) = (enabled.get("notification"), notification::NotificationDaemon::detect()) {
+        let notifications = Arc::new(notification::Notifications::new(daemon, ctx.config.notification.clone()));
+        spawn_monitor("notification", interval, move || {
+            let notifications = notifications.clone();
+            async move { notification::monitor(&notifications).await }
+        }, &ctx);
     }
-    if command_exists("dunst") {
-        spawn_monitor("notification", Duration::from_secs(600), notification_monitor, update_tx.clone(), trigger_sub(), args.profile);
+    if let Some(&interval) = enabled.get("privacy") {
+        spawn_monitor("privacy", interval, privacy_monitor, &ctx);
+    }
+    if let (Some(&interval), Some(command)) = (enabled.get("agenda"), agenda_command(&ctx.config)) {
+        let (lead, horizon) = (ctx.config.agenda.lead_minutes, ctx.config.agenda.horizon_hours);
+        cadence::spawn_two_cadence(
+            "agenda",
+            interval,
+            Duration::from_secs(60),
+            move || {
+                let command = command.clone();
+                async move { agenda::fetch(&command).await }
+            },
+            move |events| agenda::render(events.map(Vec::as_slice).unwrap_or_default(), chrono::Local::now(), lead, horizon),
+            &ctx,
+        );
+    }
+    if let (Some(&interval), Some(tool)) = (enabled.get("timetrack"), timetrack::detect(ctx.config.timetrack.tool)) {
+        // Poll the tracker every few minutes, but advance the elapsed time every minute.
+        cadence::spawn_two_cadence(
+            "timetrack",
+            interval,
+            Duration::from_secs(60),
+            move || timetrack::fetch(tool),
+            |session| timetrack::render(session.and_then(Option::as_ref), chrono::Local::now()),
+            &ctx,
+        );
+    }
+    if let Some(&interval) = enabled.get("ticker") {
+        let ticker = Arc::new(ticker::Ticker::new(http.clone(), ctx.config.ticker.clone()));
+        spawn_monitor("ticker", interval, move || {
+            let ticker = ticker.clone();
+            async move { ticker::monitor(&ticker).await }
+        }, &ctx);
+    }
+    if let Some(&interval) = enabled.get("weather") {
+        let weather = Arc::new(weather::Weather::new(http.clone(), ctx.config.weather.clone()));
+        spawn_monitor("weather", interval, move || {
+            let weather = weather.clone();
+            async move { weather::monitor(&weather).await }
+        }, &ctx);
+    }
+    if let Some(&interval) = enabled.get("idle") {
+        let (has_xset, has_inhibit) = (command_exists("xset"), command_exists("systemd-inhibit"));
+        spawn_monitor("idle", interval, move || idle_monitor(has_xset, has_inhibit), &ctx);
     }
 
-    tokio::spawn(trigger_listener(trigger_tx));
+    // Dry runs and benchmarks stop here, before anything that would touch a running bar's
+    // trigger files or socket.
+    if args.dry_run {
+        return dry_run(&detected, &mut update_rx).await;
+    }
+    #[cfg(feature = "benchmark")]
+    if args.benchmark {
+        let outcomes = first_results(&detected, &mut update_rx).await;
+        let results = outcomes.into_iter().filter_map(|(id, (outcome, _))| match outcome {
+            Outcome::Success(text) => Some((id, text)),
+            _ => None,
+        }).collect();
+        let colors = colors.lock().unwrap().clone();
+        return benchmark::run(&ctx.config, &registry, &results, &colors);
+    }
 
-    while let Some(update) = update_rx.recv().await {
+    let visibility = Visibility::new(&registry, &ctx.config.minimal_modules);
+    visibility::clear_stale(Path::new(TRIGGER_DIR));
+    // A message left over from before a restart is no longer news.
+    let _ = fs::remove_file(Path::new(TRIGGER_DIR).join(overlay::TRIGGER));
+    let overlay = overlay::Overlay::default();
+    let socket = ctx.config.trigger_socket.clone().unwrap_or_else(default_trigger_socket);
+    let debounce = ctx.config.trigger_debounce();
+    tokio::spawn(trigger_listener(trigger_tx, registry.clone(), visibility.clone(), overlay.clone(), socket, debounce, results.clone()));
+    if let Some(port) = args.metrics_port {
+        let (results, stats) = (results.clone(), stats.clone());
+        tokio::spawn(async move {
+            if let Err(e) = metrics::serve(port, results, stats).await {
+                tracing::error!("Metrics server stopped: {}", e);
+            }
+        });
+    }
+    tokio::spawn(stats_dumper(stats));
+    let clock = Arc::new(Mutex::new(clock::ClockWatch::new(Instant::now(), std::time::SystemTime::now())));
+    tokio::spawn(clock::watch(ctx.trigger_tx.clone(), registry.clone(), clock.clone()));
+    #[cfg(target_os = "linux")]
+    tokio::spawn(netlink::watch(ctx.trigger_tx.clone(), registry.clone()));
+    #[cfg(target_os = "linux")]
+    tokio::spawn(power::watch(ctx.low_power.clone(), ctx.config.clone(), registry.clone(), ctx.trigger_tx.clone()));
+    tokio::spawn(colors::reload_on_sighup(colors.clone(), ctx.config.clone(), registry.clone()));
+
+    let max_update_age = ctx.config.max_update_age();
+    let min_update_interval = ctx.config.min_update_interval();
+    let mut failures = FailureTracker::new(ctx.config.error_marker_after, ctx.config.show_errors);
+    let state = ctx.config.persist_state.then(state::State::new).flatten();
+    let state_file = ctx.config.state_file.clone().map(|path| {
+        let (tx, rx) = tokio::sync::watch::channel(String::new());
+        tokio::spawn(write_state_file(path, rx));
+        tx
+    });
+    let mut restored: HashSet<ModuleId> = HashSet::new();
+    if let Some(state) = &state {
+        let max_ages: Vec<(ModuleId, Duration)> = registry
+            .order()
+            .iter()
+            .filter_map(|id| {
+                let interval = *enabled.get(&**id)?;
+                let max_age = ctx.config.module(id).persist_max_age_secs.map(Duration::from_secs).unwrap_or(interval * 3);
+                Some((id.clone(), max_age))
+            })
+            .collect();
+        let mut results = results.lock().unwrap();
+        for (id, text) in state.restore(&max_ages) {
+            failures.restore(&id, text.clone());
+            results.modules.insert(id.clone(), text);
+            restored.insert(id);
+        }
+        tracing::info!("Restored {} module values from the last run", restored.len());
+        tokio::spawn(state::run(state.clone()));
+    }
+    let mut sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+    let mut sigint = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())?;
+    // Hold back the first write until every module that showed a placeholder has reported, or the
+    // grace period is over, so the bar appears complete instead of filling in piecemeal.
+    let startup_deadline = tokio::time::Instant::now() + ctx.config.startup_grace();
+    let mut waiting: HashSet<ModuleId> = HashSet::new();
+    let mut starting = true;
+    loop {
+        let overlay_deadline = overlay.deadline();
+        let mut next = tokio::select! {
+            update = update_rx.recv() => match update {
+                Some(update) => Some(update),
+                None => break,
+            },
+            _ = visibility.changed() => None,
+            _ = overlay.changed() => None,
+            _ = tokio::time::sleep_until(overlay_deadline.unwrap_or(startup_deadline)), if overlay_deadline.is_some() => {
+                overlay.clear_expired();
+                None
+            }
+            _ = tokio::time::sleep_until(startup_deadline), if starting => None,
+            _ = sigterm.recv() => break,
+            _ = sigint.recv() => break,
+        };
+        if next.is_some() && !min_update_interval.is_zero() {
+            // Give modules that update at the same moment a chance to land in the same write.
+            tokio::time::sleep(min_update_interval).await;
+        }
         let mut results_guard = results.lock().unwrap();
-        results_guard.insert(update.id, update.value);
-        let bar_string = assemble_bar(&results_guard);
-        drop(results_guard);
-        set_xroot_name(&bar_string);
+        // Apply everything already queued so a burst of updates costs a single write per sink.
+        while let Some(update) = next.take().or_else(|| update_rx.try_recv().ok()) {
+            let age = clock.lock().unwrap().age(update.created, Instant::now());
+            if age > max_update_age {
+                // Rerun the module instead of showing a value this old.
+                tracing::debug!("Dropping stale update for {} ({:?} old)", update.id, age);
+                let _ = ctx.trigger_tx.send(update.id);
+                continue;
+            }
+            if starting {
+                // A restored value is as good as a first result.
+                if matches!(update.outcome, Outcome::Placeholder(_)) && !restored.contains(&update.id) {
+                    waiting.insert(update.id.clone());
+                } else {
+                    waiting.remove(&update.id);
+                }
+            }
+            if let (Some(state), Outcome::Success(text)) = (&state, &update.outcome) {
+                state.record(&update.id, text);
+            }
+            let text = failures.apply(&update.id, update.outcome);
+            results_guard.modules.insert(update.id, text);
+        }
+        if starting {
+            if !waiting.is_empty() && tokio::time::Instant::now() < startup_deadline {
+                continue;
+            }
+            starting = false;
+            tracing::debug!(target: "monitor", still_waiting = waiting.len(), "Writing the first bar");
+        }
+        let colors_guard = colors.lock().unwrap();
+        let mut hidden = visibility.hidden();
+        hide_rules.apply(&results_guard.modules, &mut hidden);
+        let overlay_text = overlay.text();
+        for sink in &mut sinks {
+            let bar_string = sink.render(&results_guard.modules, &colors_guard, &hidden, overlay_text.as_deref());
+            sink.write(bar_string);
+        }
+        if let Some(state_file) = &state_file {
+            let json = bar::assemble_json(&results_guard.modules);
+            state_file.send_if_modified(|current| {
+                let changed = *current != json;
+                if changed {
+                    *current = json;
+                }
+                changed
+            });
+        }
+    }
+
+    // Monitors finish the run they are in and stop; no bar is written after this.
+    tracing::info!("Shutting down");
+    ctx.shutdown.cancel();
+    ctx.supervisor.shutdown(SHUTDOWN_TIMEOUT).await;
+    if let Some(state) = &state {
+        state.save_or_warn();
     }
+    Ok(())
 }
 
+/// Writes each new `state_file` snapshot from `rx` in its own task, so a slow disk never holds up
+/// the bar. Snapshots arriving during a write are coalesced into the next one.
+async fn write_state_file(path: PathBuf, mut rx: tokio::sync::watch::Receiver<String>) {
+    let tmp = path.with_extension("tmp");
+    let mut failing = false;
+    while rx.changed().await.is_ok() {
+        let json = rx.borrow_and_update().clone();
+        let result = match tokio::fs::write(&tmp, json).await {
+            Ok(()) => tokio::fs::rename(&tmp, &path).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) if std::mem::take(&mut failing) => tracing::info!("Writing the state file {} works again", path.display()),
+            Ok(()) => {}
+            Err(e) if !failing => {
+                tracing::error!("Failed to write the state file {}: {}", path.display(), e);
+                failing = true;
+            }
+            Err(_) => {}
+        }
+    }
+}
+
+/// A module the bar knows about and whether its dependencies are present on this system.
+#[derive(Debug, Clone)]
+struct ModuleInfo {
+    id: String,
+    enabled: bool,
+    interval: Duration,
+    /// External commands, files or config keys the module needs; any one of them is enough.
+    requires: Vec<String>,
+}
+
+impl ModuleInfo {
+    /// The module is enabled when any of `requires` is satisfied.
+    fn new(id: &str, interval_secs: u64, requires: &[Dependency]) -> Self {
+        ModuleInfo {
+            id: id.to_string(),
+            enabled: requires.iter().any(Dependency::satisfied),
+            interval: Duration::from_secs(interval_secs),
+            requires: requires.iter().filter_map(Dependency::label).map(str::to_string).collect(),
+        }
+    }
+
+    /// Disables the module on other platforms, for the monitors that read Linux-only interfaces.
+    fn linux_only(mut self) -> Self {
+        self.enabled &= cfg!(target_os = "linux");
+        self
+    }
+}
+
+/// Something a module needs on this system before it is spawned.
+#[derive(Debug, Clone, Copy)]
+enum Dependency<'a> {
+    /// Always available.
+    Always,
+    /// An executable on the `PATH`.
+    Command(&'a str),
+    /// A file or directory, usually under `/sys`.
+    File(&'a str),
+    /// A config key or detection done by the module itself, shown under the given label.
+    Check(&'a str, bool),
+}
+
+impl Dependency<'_> {
+    fn satisfied(&self) -> bool {
+        match *self {
+            Dependency::Always => true,
+            Dependency::Command(cmd) => command_exists(cmd),
+            Dependency::File(path) => Path::new(path).exists(),
+            Dependency::Check(_, ok) => ok,
+        }
+    }
+
+    fn label(&self) -> Option<&str> {
+        match *self {
+            Dependency::Always => None,
+            Dependency::Command(label) | Dependency::File(label) | Dependency::Check(label, _) => Some(label),
+        }
+    }
+}
+
+/// Checks the dependencies of every built-in module. `main` spawns exactly the enabled ones.
+fn detect_modules(config: &Config, gpu_zones: &[(String, PathBuf)]) -> Vec<ModuleInfo> {
+    use Dependency::{Always, Check, Command, File};
+    let mut modules = vec![
+        ModuleInfo::new("datetime", 1, &[Always]),
+        ModuleInfo::new("datetime2", 1, &[Check("[datetime] secondary_format", config.datetime.secondary_format.is_some())]),
+    ];
+    modules.extend(config.disk.instances.iter().map(|d| ModuleInfo::new(&d.id, 30, &[Always])));
+    modules.extend([
+        ModuleInfo::new("ram", 5, &[Always]),
+        ModuleInfo::new("cpu_load", 2, &[Always]),
+        ModuleInfo::new("hog", 10, &[Check("[hog] enabled", config.hog.enabled)]),
+        ModuleInfo::new("vpn", 10, &[File("/sys/class/net")]).linux_only(),
+        ModuleInfo::new("cpu_temp", 10, &[File("/sys/class/thermal/thermal_zone0/temp")]).linux_only(),
+        ModuleInfo::new("net", 10, &[File(NETWORK_SCRIPT), Check("/sys/class/net", net::available())]),
+        ModuleInfo::new("battery", 30, &[Command("acpi"), File(POWER_SUPPLY_DIR)]).linux_only(),
+        ModuleInfo::new("ups", 60, &[Check("[ups] name", config.ups.name.is_some())]),
+        ModuleInfo::new("volume", 10, &[Check("amixer | [volume] sink + pactl | wpctl", volume::Volume::available(&config.volume))]),
+        ModuleInfo::new("brightness", 10, &[Check(brightness::BACKLIGHT_DIR, brightness::backlight().is_some())]).linux_only(),
+        // DDC/CI queries take a while and the monitor's brightness rarely changes behind our back.
+        ModuleInfo::new("ext_brightness", 60, &[Command("ddcutil")]).linux_only(),
+        ModuleInfo::new("bluetooth", 60, &[File("/sys/class/bluetooth")]),
+        // The unread count should follow new notifications closely; the paused state rarely changes.
+        ModuleInfo::new("notification", if config.notification.history { 10 } else { 600 }, &[Command("dunstctl"), Command("makoctl"), Command("swaync-client")]),
+        ModuleInfo::new("privacy", 5, &[Command("pw-dump")]),
+        ModuleInfo::new("agenda", 300, &[Command("khal"), Check("[agenda] command", config.agenda.command.is_some())]),
+        ModuleInfo::new("timetrack", 300, &[Command("timew"), Command("watson"), Check("[timetrack] tool", config.timetrack.tool.is_some())]),
+        ModuleInfo::new("ticker", 300, &[Check("[ticker] symbols", !config.ticker.symbols.is_empty())]),
+        ModuleInfo::new("weather", 900, &[Check("[weather] location", config.weather.location.is_some())]),
+        ModuleInfo::new("idle", 30, &[Command("xset"), Command("systemd-inhibit")]),
+    ]);
+    if gpu_zones.is_empty() {
+        modules.push(ModuleInfo::new("gpu_temp", 30, &[Check("GPU thermal zone", false)]));
+    }
+    modules.extend(gpu_zones.iter().map(|(id, path)| ModuleInfo::new(id, 30, &[File(&path.display().to_string())]).linux_only()));
+    modules
+}
+
+/// Thermal zones whose `type` names a GPU or GPU driver, as module ID and temperature file. A single
+/// zone is the `gpu_temp` module; several become `gpu_temp_0`, `gpu_temp_1`, ... in zone order.
+fn gpu_thermal_zones() -> Vec<(String, PathBuf)> {
+    const GPU_TYPES: [&str; 5] = ["gpu", "nvidia", "amd", "radeon", "nouveau"];
+    let Ok(entries) = fs::read_dir("/sys/class/thermal") else {
+        return Vec::new();
+    };
+    let mut zones: Vec<(u32, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let number = entry.file_name().to_str()?.strip_prefix("thermal_zone")?.parse().ok()?;
+            let zone_type = fs::read_to_string(entry.path().join("type")).ok()?.trim().to_lowercase();
+            GPU_TYPES.iter().any(|t| zone_type.contains(t)).then(|| (number, entry.path().join("temp")))
+        })
+        .collect();
+    zones.sort();
+    tracing::debug!(target: "monitor", ?zones, "Detected GPU thermal zones");
+    let single = zones.len() == 1;
+    zones
+        .into_iter()
+        .enumerate()
+        .map(|(i, (_, path))| (if single { "gpu_temp".to_string() } else { format!("gpu_temp_{}", i) }, path))
+        .collect()
+}
+
+/// Longest `--dry-run` waits for modules to report; the rest are listed as timed out.
+const DRY_RUN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The first real outcome of every enabled module and how long it took, for modules that ran
+/// once. Gives up on the rest after `DRY_RUN_TIMEOUT`.
+async fn first_results(modules: &[ModuleInfo], update_rx: &mut mpsc::Receiver<Update>) -> HashMap<ModuleId, (Outcome, Duration)> {
+    let started = Instant::now();
+    let deadline = tokio::time::Instant::now() + DRY_RUN_TIMEOUT;
+    let mut pending: HashSet<&str> = modules.iter().filter(|m| m.enabled).map(|m| m.id.as_str()).collect();
+    let mut outcomes: HashMap<ModuleId, (Outcome, Duration)> = HashMap::new();
+    while !pending.is_empty() {
+        let Ok(Some(update)) = tokio::time::timeout_at(deadline, update_rx.recv()).await else {
+            break;
+        };
+        if matches!(update.outcome, Outcome::Placeholder(_)) || !pending.remove(&*update.id) {
+            continue;
+        }
+        outcomes.insert(update.id, (update.outcome, update.created.duration_since(started)));
+    }
+    outcomes
+}
+
+async fn dry_run(modules: &[ModuleInfo], update_rx: &mut mpsc::Receiver<Update>) -> Result<()> {
+    let mut outcomes = first_results(modules, update_rx).await;
+    println!("{:<14} {:<8} {:>9}  value", "module", "status", "duration");
+    let mut failed = 0;
+    // What the bar would hold, printed as the trigger socket's `get` lists it.
+    let mut state = BarState::default();
+    for m in modules {
+        let (status, duration, value) = match outcomes.remove(m.id.as_str()) {
+            _ if !m.enabled => ("skipped", None, format!("missing {}", m.requires.join(" | "))),
+            Some((Outcome::Success(text), d)) => {
+                state.modules.insert(m.id.as_str().into(), text.clone());
+                ("ok", Some(d), if text.is_empty() { "(hidden)".to_string() } else { text })
+            }
+            Some((Outcome::Failure { message, .. }, d)) => ("error", Some(d), message),
+            Some((Outcome::Stopped(text) | Outcome::Placeholder(text), d)) => ("stopped", Some(d), text),
+            None => ("timeout", None, format!("no result within {:?}", DRY_RUN_TIMEOUT)),
+        };
+        if matches!(status, "error" | "stopped" | "timeout") {
+            failed += 1;
+        }
+        let duration = duration.map_or("-".to_string(), |d| format!("{}ms", d.as_millis()));
+        println!("{:<14} {:<8} {:>9}  {}", m.id, status, duration, value);
+    }
+    print!("\nbar state:\n{}", state);
+    if failed > 0 {
+        anyhow::bail!("{} module(s) failed", failed);
+    }
+    Ok(())
+}
+
+fn print_modules(modules: &[ModuleInfo]) {
+    println!("{:<14} {:<8} {:>9}  requires", "module", "enabled", "interval");
+    for m in modules {
+        let requires = if m.requires.is_empty() { "-".to_string() } else { m.requires.join(" | ") };
+        println!("{:<14} {:<8} {:>9}  {}", m.id, if m.enabled { "yes" } else { "no" }, format_interval(m.interval), requires);
+    }
+}
+
+fn format_interval(interval: Duration) -> String {
+    if interval.is_zero() {
+        "trigger".to_string()
+    } else {
+        format!("{:?}", interval)
+    }
+}
+
+fn parse_interval(arg: &str) -> Result<(String, Duration), String> {
+    let (id, secs) = arg.split_once('=').ok_or("expected ID=SECONDS")?;
+    let secs: f64 = secs.parse().map_err(|e| format!("invalid seconds '{}': {}", secs, e))?;
+    let interval = Duration::try_from_secs_f64(secs).map_err(|e| format!("invalid seconds '{}': {}", secs, e))?;
+    Ok((id.to_string(), interval))
+}
+
+fn parse_module_filter(arg: &str) -> Result<regex::Regex, String> {
+    regex::Regex::new(arg).map_err(|e| format!("invalid regex: {}", e))
+}
+
+/// Applies `--interval` overrides. Unknown module IDs are an error.
+fn apply_interval_overrides(modules: &mut [ModuleInfo], overrides: &[(String, Duration)]) -> Result<()> {
+    for (id, interval) in overrides {
+        match modules.iter_mut().find(|m| &m.id == id) {
+            Some(module) => module.interval = *interval,
+            None => {
+                let valid: Vec<&str> = modules.iter().map(|m| m.id.as_str()).collect();
+                anyhow::bail!("Unknown module '{}' in --interval, valid modules: {}", id, valid.join(", "));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The configured agenda command, or a `khal list` invocation when khal is installed.
+fn agenda_command(config: &Config) -> Option<String> {
+    config.agenda.command.clone().or_else(|| {
+        command_exists("khal").then(|| r#"khal list now 24h --day-format "" --format "{start-date-long} {start-time} {title}""#.to_string())
+    })
+}
+
+/// Builds the display order from config, expanding the `disk` slot into the configured disk instances.
+fn build_registry(config: &Config, gpu_zones: &[(String, PathBuf)]) -> ModuleRegistry {
+    if let Some(order) = &config.order {
+        return ModuleRegistry::new(order);
+    }
+    ModuleRegistry::new(DEFAULT_MODULE_ORDER.iter().flat_map(|&id| -> Vec<&str> {
+        if id == "disk" {
+            config.disk.instances.iter().map(|d| d.id.as_str()).collect()
+        } else if id == "gpu_temp" && !gpu_zones.is_empty() {
+            gpu_zones.iter().map(|(id, _)| id.as_str()).collect()
+        } else {
+            vec![id]
+        }
+    }))
+}
+
+/// Checked before each run of a module; while it returns false the run is skipped and the module
+/// hidden. Must be cheap, since it runs synchronously.
+type Condition = Arc<dyn Fn() -> bool + Send + Sync>;
+
+/// Called with the module ID and its new text whenever a successful run's text differs from the
+/// previous one. Runs synchronously in the module's task, so slow work belongs in a spawned task.
+type OnChange = Arc<dyn Fn(&str, &str) + Send + Sync>;
+
+/// Runs the module's configured `on_change` command in the background.
+fn on_change_command(config: &Config, id: &str) -> Option<OnChange> {
+    let command = config.module(id).on_change.clone()?;
+    Some(Arc::new(move |id: &str, text: &str| {
+        let (command, id, text) = (command.clone(), id.to_string(), text.to_string());
+        tokio::spawn(async move {
+            if let Err(e) = command::run_command_timeout("sh", &["-c", &command, "sh", &id, &text], None).await {
+                tracing::warn!(target: "monitor", "on_change command for '{}' failed: {}", id, e);
+            }
+        });
+    }))
+}
+
+/// Runs `monitor_fn` every `interval_duration` and on triggers. The module stops after its
+/// current run once `ctx.shutdown` is cancelled.
 fn spawn_monitor<F, Fut>(
-    id: &'static str,
+    id: &str,
     interval_duration: Duration,
     monitor_fn: F,
-    tx: mpsc::Sender<Update>,
-    mut trigger_rx: broadcast::Receiver<&'static str>,
-    profile: bool,
+    ctx: &MonitorContext,
 ) where
     F: Fn() -> Fut + Send + Sync + 'static,
     Fut: std::future::Future<Output = Result<String>> + Send,
 {
+    spawn_monitor_with(id, interval_duration, None, on_change_command(&ctx.config, id), monitor_fn, ctx)
+}
+
+/// Like `spawn_monitor`, with an optional `condition` that can switch the module off at runtime
+/// and an `on_change` callback for when its text changes.
+fn spawn_monitor_with<F, Fut>(
+    id: &str,
+    interval_duration: Duration,
+    condition: Option<Condition>,
+    on_change: Option<OnChange>,
+    monitor_fn: F,
+    ctx: &MonitorContext,
+) where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: std::future::Future<Output = Result<String>> + Send,
+{
+    let Some(id) = ctx.registry.get(id) else {
+        tracing::warn!(target: "monitor", "Module '{}' is not in the display order, not starting it", id);
+        return;
+    };
     let monitor_fn = Arc::new(monitor_fn);
+    let module_config = ctx.config.module(&id);
+    let placeholder = module_config.placeholder.clone().or_else(|| ctx.config.placeholder.clone()).unwrap_or_else(|| id.to_string());
+    let update_tx = ctx.update_tx.clone();
+    let trigger_tx = ctx.trigger_tx.clone();
+    let stats = ctx.stats.clone();
+    let profile = ctx.profile;
+    let min_trigger_interval = ctx.config.min_trigger_interval();
+    let started = Arc::new(AtomicBool::new(false));
+    let max_failures = module_config.max_consecutive_failures;
+    let backoff = module_config.max_backoff_secs.map(|secs| (Duration::from_secs(secs), module_config.backoff_after.max(1)));
+    let failure_text = module_config.failure_text.clone().unwrap_or_else(|| format!("{} err", id));
+    let low_power = ctx.low_power.clone();
+    let dry_run = ctx.dry_run;
 
-    let task = move || {
+    let task_id = id.clone();
+    let task = Arc::new(move || {
         let monitor_fn = Arc::clone(&monitor_fn);
+        let stats = stats.clone();
+        let id = task_id.clone();
+        let module_config = module_config.clone();
+        let skip = (module_config.low_power_disable && low_power.load(Ordering::Relaxed)) || condition.as_ref().is_some_and(|c| !c());
+        // With --profile, every run gets its own span under the module's.
+        let span = if profile { tracing::info_span!("run") } else { tracing::Span::none() };
         async move {
+            use chrono::Timelike;
+            if skip || !module_config.visible_at(chrono::Local::now().hour()) {
+                return Ok(String::new());
+            }
             let start = Instant::now();
-            let result = monitor_fn().await;
+            let command_timeout = Duration::from_millis(module_config.command_timeout_ms);
+            let result = command::MODULE_TIMEOUT.scope(command_timeout, monitor_fn()).await;
+            stats.record(&id, start.elapsed(), result.is_ok());
             if profile {
-                tracing::info!(module = id, duration_ms = start.elapsed().as_millis(), "Module executed");
+                tracing::info!(target: "monitor", duration_ms = start.elapsed().as_millis(), "Module executed");
             }
             result
         }
-    };
+        .instrument(span)
+    });
 
-    tokio::spawn(async move {
-        match task().await {
-            Ok(value) => {
-                if tx.send(Update { id, value }).await.is_err() {
-                    return;
-                }
-            }
-            Err(e) => {
-                tracing::warn!("Disabling monitor '{}' because initial run failed: {}", id, e);
+    let (module_id, ctx_low_power, task_cancel) = (id.clone(), ctx.low_power.clone(), ctx.shutdown.clone());
+    let factory: supervisor::TaskFactory = Arc::new(move || {
+        let (id, tx, task, placeholder, cancel) = (module_id.clone(), update_tx.clone(), task.clone(), placeholder.clone(), task_cancel.clone());
+        let low_power = ctx_low_power.clone();
+        let (failure_text, on_change) = (failure_text.clone(), on_change.clone());
+        let mut trigger_rx = trigger_tx.subscribe();
+        let restarted = started.swap(true, Ordering::Relaxed);
+        let span = tracing::info_span!("module", module_id = %id);
+        Box::pin(async move {
+            // Claim the slot right away so the bar doesn't reflow as modules come online.
+            if !restarted && tx.send(Update::new(id.clone(), Outcome::Placeholder(placeholder))).await.is_err() {
                 return;
             }
-        }
-        let mut interval = tokio::time::interval(interval_duration);
-        loop {
-            tokio::select! {
-                _ = interval.tick() => {},
-                Ok(triggered_id) = trigger_rx.recv() => {
-                    if triggered_id != id { continue; }
-                    tracing::info!("Triggered update for {}", id);
+            // A zero interval means the module only runs on triggers.
+            let periodic = !interval_duration.is_zero();
+            let mut last_run = tokio::time::Instant::now();
+            // A trigger arriving too soon after the last run is coalesced into a single deferred run.
+            let mut deferred: Option<tokio::time::Instant> = None;
+            let mut failures: u8 = 0;
+            let mut first_run = true;
+            // The first result isn't a change, so a module that is already on doesn't report it.
+            let mut previous: Option<String> = None;
+            // With `max_backoff_secs`, grows while the text stays the same.
+            let mut interval = interval_duration;
+            let mut unchanged: u32 = 0;
+            loop {
+                if !std::mem::take(&mut first_run) {
+                    tokio::select! {
+                        _ = cancel.cancelled() => break,
+                        _ = tokio::time::sleep_until(last_run + period(interval, &low_power)), if periodic => {},
+                        _ = tokio::time::sleep_until(deferred.unwrap_or(last_run)), if deferred.is_some() => {
+                            tracing::info!(target: "monitor", "Running deferred trigger for {}", id);
+                        }
+                        Ok(triggered_id) = trigger_rx.recv() => {
+                            if triggered_id != id { continue; }
+                            let next_allowed = last_run + min_trigger_interval;
+                            if tokio::time::Instant::now() < next_allowed {
+                                deferred = Some(next_allowed);
+                                continue;
+                            }
+                            tracing::info!(target: "monitor", "Triggered update for {}", id);
+                        }
+                    }
                 }
-            }
-            match task().await {
-                Ok(value) => {
-                    if tx.send(Update { id, value }).await.is_err() {
-                        break;
+                deferred = None;
+                last_run = tokio::time::Instant::now();
+                let update = match task().await {
+                    Ok(value) => {
+                        failures = 0;
+                        let same = previous.as_ref() == Some(&value);
+                        if let Some(on_change) = on_change.as_ref().filter(|_| previous.is_some() && !same) {
+                            on_change(&id, &value);
+                        }
+                        if let Some((max, after)) = backoff.filter(|_| periodic) {
+                            unchanged = if same { unchanged + 1 } else { 0 };
+                            let next = if same && unchanged >= after {
+                                unchanged = 0;
+                                (interval * 2).min(max.max(interval_duration))
+                            } else if same {
+                                interval
+                            } else {
+                                interval_duration
+                            };
+                            if next != interval {
+                                tracing::debug!(target: "monitor", module = %id, ?next, "Changed polling interval");
+                                interval = next;
+                            }
+                        }
+                        previous = Some(value.clone());
+                        Update::success(id.clone(), value)
+                    }
+                    Err(e) => {
+                        failures = failures.saturating_add(1);
+                        if max_failures > 0 && failures >= max_failures {
+                            tracing::warn!(target: "monitor", module = %id, failures, reason = %e, "Monitor permanently disabled");
+                            let _ = tx.send(Update::new(id.clone(), Outcome::Stopped(failure_text))).await;
+                            return;
+                        }
+                        tracing::error!(target: "monitor", "Monitor '{}' failed: {}", id, e);
+                        Update::failure(id.clone(), &e)
+                    }
+                };
+                if tx.send(update).await.is_err() || dry_run {
+                    break;
+                }
+                // Triggers that arrived while this run was in flight are satisfied by it.
+                while let Ok(pending) = trigger_rx.try_recv() {
+                    if pending == id {
+                        tracing::debug!(target: "monitor", "Coalesced trigger for {}", id);
                     }
                 }
-                Err(e) => tracing::error!("Monitor '{}' failed: {}", id, e),
             }
+        }.instrument(span))
+    });
+    ctx.supervisor.spawn(id, factory);
+}
+
+/// A module's interval, doubled in low-power mode.
+fn period(interval: Duration, low_power: &AtomicBool) -> Duration {
+    if low_power.load(Ordering::Relaxed) {
+        interval * 2
+    } else {
+        interval
+    }
+}
+
+async fn trigger_listener(
+    tx: broadcast::Sender<ModuleId>,
+    registry: Arc<ModuleRegistry>,
+    visibility: Visibility,
+    overlay: overlay::Overlay,
+    socket: PathBuf,
+    debounce: Duration,
+    results: Arc<Mutex<BarState>>,
+) {
+    signal_triggers(&tx, &registry);
+    let (socket_tx, socket_registry, socket_visibility, socket_overlay) = (tx.clone(), registry.clone(), visibility.clone(), overlay.clone());
+    tokio::spawn(async move {
+        if let Err(e) = socket_triggers(&socket, socket_tx, socket_registry, socket_visibility, socket_overlay, results).await {
+            tracing::warn!("Socket triggers unavailable on {}, file triggers still work: {}", socket.display(), e);
         }
     });
+    loop {
+        if let Err(e) = watch_triggers(tx.clone(), registry.clone(), &visibility, &overlay, debounce).await {
+            tracing::error!("Trigger watcher failed, restarting in 5s: {}", e);
+        }
+        tokio::time::sleep(Duration::from_secs(5)).await;
+    }
 }
 
-async fn trigger_listener(tx: broadcast::Sender<&'static str>) -> Result<()> {
+/// Maps `SIGRTMIN+N` to the N-th module in display order, i3blocks style:
+/// `pkill -SIGRTMIN+1 dwm-status-bar-rs` refreshes the second module.
+fn signal_triggers(tx: &broadcast::Sender<ModuleId>, registry: &ModuleRegistry) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let (rtmin, rtmax) = (libc::SIGRTMIN(), libc::SIGRTMAX());
+    let mut mapping = Vec::new();
+    for (offset, id) in registry.order().iter().enumerate() {
+        let signum = rtmin + offset as i32;
+        if signum > rtmax {
+            tracing::warn!("Not enough real-time signals, modules from {} on have no signal trigger", id);
+            break;
+        }
+        let mut stream = match signal(SignalKind::from_raw(signum)) {
+            Ok(stream) => stream,
+            Err(e) => {
+                tracing::warn!("Cannot listen for SIGRTMIN+{}: {}", offset, e);
+                continue;
+            }
+        };
+        mapping.push(format!("SIGRTMIN+{}={}", offset, id));
+        let (tx, id) = (tx.clone(), id.clone());
+        tokio::spawn(async move {
+            while stream.recv().await.is_some() {
+                tracing::debug!("Signal trigger for {}", id);
+                let _ = tx.send(id.clone());
+            }
+        });
+    }
+    tracing::info!("Signal triggers: {}", mapping.join(" "));
+}
+
+async fn watch_triggers(
+    tx: broadcast::Sender<ModuleId>,
+    registry: Arc<ModuleRegistry>,
+    visibility: &Visibility,
+    overlay: &overlay::Overlay,
+    debounce: Duration,
+) -> Result<()> {
     use notify::{Error, RecursiveMode};
     use notify_debouncer_mini::{new_debouncer, DebouncedEvent};
 
-    let mut debouncer = new_debouncer(Duration::from_millis(100), move |res: Result<Vec<DebouncedEvent>, Error>| {
-        if let Ok(events) = res {
-            for event in events {
-                if let Some(id_str) = event.path.file_name().and_then(|s| s.to_str()) {
-                    if let Some(id) = MODULE_ORDER.iter().find(|&&m| m == id_str) {
-                        let _ = tx.send(id);
+    fs::create_dir_all(TRIGGER_DIR)?;
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    let mut debouncer = new_debouncer(debounce, move |res: Result<Vec<DebouncedEvent>, Error>| {
+        let _ = event_tx.send(res);
+    })?;
+    debouncer.watcher().watch(Path::new(TRIGGER_DIR), RecursiveMode::NonRecursive)?;
+
+    // notify only reports changes, so files created while the bar wasn't running are handled here.
+    for entry in fs::read_dir(TRIGGER_DIR)?.flatten() {
+        fire_trigger(&entry.path(), &tx, &registry, visibility, overlay);
+    }
+
+    while let Some(res) = event_rx.recv().await {
+        for event in res? {
+            fire_trigger(&event.path, &tx, &registry, visibility, overlay);
+        }
+    }
+    anyhow::bail!("Watcher event channel closed")
+}
+
+/// `$XDG_RUNTIME_DIR/dwm-bar.trigger.sock`, or a file in /tmp without a runtime directory.
+fn default_trigger_socket() -> PathBuf {
+    env::var_os("XDG_RUNTIME_DIR").map(PathBuf::from).unwrap_or_else(|| PathBuf::from("/tmp")).join("dwm-bar.trigger.sock")
+}
+
+/// Bytes read from one socket trigger connection before it is dropped.
+const MAX_SOCKET_TRIGGER_BYTES: u64 = 4096;
+/// Socket line that lists every module's current text instead of triggering.
+const QUERY_GET: &str = "get";
+
+/// Accepts trigger names over a Unix socket, one per line, as an alternative to trigger files:
+/// `echo volume | nc -U $XDG_RUNTIME_DIR/dwm-bar.trigger.sock`. An overlay message follows the
+/// name on the same line (`overlay 5 Build done`).
+async fn socket_triggers(
+    path: &Path,
+    tx: broadcast::Sender<ModuleId>,
+    registry: Arc<ModuleRegistry>,
+    visibility: Visibility,
+    overlay: overlay::Overlay,
+    results: Arc<Mutex<BarState>>,
+) -> Result<()> {
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+
+    // A socket file left behind by a previous run would make `bind` fail; one that still accepts
+    // connections belongs to another running bar.
+    if path.exists() {
+        if UnixStream::connect(path).await.is_ok() {
+            anyhow::bail!("another instance is listening");
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    tracing::info!("Listening for triggers on {}", path.display());
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let (tx, registry, visibility, overlay, results) = (tx.clone(), registry.clone(), visibility.clone(), overlay.clone(), results.clone());
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            // Bounded so a misbehaving client can't make the bar buffer without limit.
+            let mut lines = BufReader::new(reader.take(MAX_SOCKET_TRIGGER_BYTES)).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                let line = line.trim();
+                // Queries are answered on the same connection: JSON ones in JSON, a bare `get`
+                // with the readable listing.
+                let reply = if line.starts_with('{') {
+                    Some(results.lock().unwrap().answer(line) + "\n")
+                } else if line == QUERY_GET {
+                    Some(results.lock().unwrap().to_string())
+                } else {
+                    None
+                };
+                if let Some(reply) = reply {
+                    if writer.write_all(reply.as_bytes()).await.is_err() {
+                        break;
                     }
+                    continue;
+                }
+                let (name, message) = line.split_once(' ').unwrap_or((line, ""));
+                if !name.is_empty() && !dispatch_trigger(name, || Some(message.to_string()), &tx, &registry, &visibility, &overlay) {
+                    tracing::debug!("Ignoring unknown socket trigger '{}'", name);
                 }
             }
+        });
+    }
+}
+
+/// Acts on a trigger name from any source: refreshes the named module (or every module for
+/// `all`), changes visibility, or shows the overlay message from `message`. Returns false when
+/// nothing handles the name.
+fn dispatch_trigger(
+    name: &str,
+    message: impl FnOnce() -> Option<String>,
+    tx: &broadcast::Sender<ModuleId>,
+    registry: &ModuleRegistry,
+    visibility: &Visibility,
+    overlay: &overlay::Overlay,
+) -> bool {
+    if name == overlay::TRIGGER {
+        if let Some(message) = message() {
+            overlay.show(&message);
         }
-    })?;
-    debouncer.watcher().watch(Path::new(TRIGGER_DIR), RecursiveMode::NonRecursive)?;
-    std::future::pending::<()>().await;
+        return true;
+    }
+    if visibility.handle(name, registry) {
+        return true;
+    }
+    let ids: Vec<ModuleId> = if name == TRIGGER_ALL {
+        registry.order().to_vec()
+    } else if name == notification::CLEAR_TRIGGER {
+        notification::request_clear();
+        registry.get("notification").into_iter().collect()
+    } else {
+        registry.get(name).into_iter().collect()
+    };
+    for id in &ids {
+        let _ = tx.send(id.clone());
+    }
+    !ids.is_empty()
+}
+
+/// Broadcasts the module named by a trigger file (or every module for `all`), then removes
+/// the file so the next `touch` creates it afresh and reliably produces an event.
+fn fire_trigger(path: &Path, tx: &broadcast::Sender<ModuleId>, registry: &ModuleRegistry, visibility: &Visibility, overlay: &overlay::Overlay) {
+    let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+        return;
+    };
+    // Our own removal is reported as an event too; a missing file means it was already handled.
+    if !path.exists() {
+        return;
+    }
+    let read_message = || match fs::read_to_string(path) {
+        Ok(line) => Some(line),
+        Err(e) => {
+            tracing::warn!("Cannot read overlay message {}: {}", path.display(), e);
+            None
+        }
+    };
+    if !dispatch_trigger(name, read_message, tx, registry, visibility, overlay) {
+        return;
+    }
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            tracing::warn!("Failed to remove trigger file {}: {}", path.display(), e);
+        }
+    }
+}
+
+async fn stats_dumper(stats: Stats) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let mut sigusr2 = signal(SignalKind::user_defined2())?;
+    while sigusr2.recv().await.is_some() {
+        tracing::info!("Module statistics:\n{}", stats.table());
+    }
     Ok(())
 }
 
-fn assemble_bar(results: &HashMap<&'static str, String>) -> String {
-    let parts: Vec<String> = MODULE_ORDER
-        .iter()
-        .filter_map(|&id| results.get(id).cloned().filter(|s| !s.is_empty()))
-        .collect();
-    format!(" {} ", parts.join(" | "))
+// --- Individual Monitor Functions ---
+
+/// Returns `format` if chrono can render it, otherwise warns and falls back to the default.
+fn checked_datetime_format(format: &str) -> String {
+    if !check::valid_datetime_format(format) {
+        tracing::warn!("Invalid datetime format '{}', using '{}'", format, config::DEFAULT_DATETIME_FORMAT);
+        return config::DEFAULT_DATETIME_FORMAT.to_string();
+    }
+    format.to_string()
 }
 
-fn set_xroot_name(name: &str) {
-    if let Err(e) = Command::new("xsetroot").arg("-name").arg(name).status() {
-        tracing::error!("Failed to run xsetroot: {}", e);
+/// The locale for weekday and month names: the configured one, or the first of `LC_ALL`, `LC_TIME`
+/// and `LANG` that is set, as date(1) does. Unsupported locales fall back to English.
+fn datetime_locale(configured: Option<&str>) -> chrono::Locale {
+    let name = configured.map(str::to_string).or_else(|| {
+        ["LC_ALL", "LC_TIME", "LANG"].iter().find_map(|var| env::var(var).ok().filter(|v| !v.is_empty()))
+    });
+    let Some(name) = name else {
+        return chrono::Locale::POSIX;
+    };
+    // `de_DE.UTF-8` and `de_DE@euro` name the same locale as `de_DE`.
+    let base = name.split(['.', '@']).next().unwrap_or_default();
+    if base == "C" || base == "POSIX" {
+        return chrono::Locale::POSIX;
     }
+    chrono::Locale::try_from(base).unwrap_or_else(|_| {
+        tracing::warn!("Unsupported datetime locale '{}', using English names", name);
+        chrono::Locale::POSIX
+    })
 }
 
-async fn run_command(cmd: &str, args: &[&str]) -> Result<String> {
-    let output = tokio::process::Command::new(cmd).args(args).output().await?;
-    if output.status.success() {
-        Ok(String::from_utf8(output.stdout)?.trim().to_string())
-    } else {
-        anyhow::bail!("Command '{}' failed: {}", cmd, String::from_utf8_lossy(&output.stderr))
+/// `unsynced`, when checked, adds a `(!)` marker while the clock isn't NTP-synchronized.
+async fn datetime_monitor(format: Arc<str>, locale: chrono::Locale, countdown: Option<Arc<Countdown>>, unsynced: Option<Arc<AtomicBool>>) -> Result<String> {
+    let mut clock = chrono::Local::now().format_localized(&format, locale).to_string();
+    if unsynced.is_some_and(|unsynced| unsynced.load(Ordering::Relaxed)) {
+        clock.push_str(" (!)");
     }
+    Ok(match countdown {
+        Some(countdown) => format!("{} [{}]", clock, countdown.render(chrono::Utc::now())),
+        None => clock,
+    })
 }
 
-// --- Individual Monitor Functions ---
+/// The `countdown_to` event shown after the primary clock.
+struct Countdown {
+    target: chrono::DateTime<chrono::Utc>,
+    label: Option<String>,
+}
+
+impl Countdown {
+    /// `None`, with a warning, when `target` isn't an RFC 3339 timestamp.
+    fn new(target: &str, label: Option<String>) -> Option<Arc<Self>> {
+        match chrono::DateTime::parse_from_rfc3339(target) {
+            Ok(target) => Some(Arc::new(Countdown { target: target.with_timezone(&chrono::Utc), label })),
+            Err(e) => {
+                tracing::warn!("Ignoring datetime countdown_to '{}', not an RFC 3339 timestamp: {}", target, e);
+                None
+            }
+        }
+    }
 
-async fn datetime_monitor() -> Result<String> {
-    Ok(chrono::Local::now().format("%a %d %b %H:%M:%S").to_string())
+    /// `3d 4h to vacation` before the event, `vacation was 2h ago` after it.
+    fn render(&self, now: chrono::DateTime<chrono::Utc>) -> String {
+        let remaining = self.target.signed_duration_since(now);
+        let span = format_span(remaining.num_seconds().unsigned_abs());
+        match (&self.label, remaining.num_seconds() >= 0) {
+            (Some(label), true) => format!("{} to {}", span, label),
+            (Some(label), false) => format!("{} was {} ago", label, span),
+            (None, true) => span,
+            (None, false) => format!("{} ago", span),
+        }
+    }
 }
 
-async fn disk_monitor(sys: Arc<Mutex<System>>) -> Result<String> {
-    let mut sys = sys.lock().unwrap();
-    sys.refresh_disks_list();
-    let root_disk = sys.disks().iter().find(|d| d.mount_point() == Path::new("/")).ok_or_else(|| anyhow::anyhow!("'/' disk not found"))?;
-    let used_pct = (root_disk.total_space() - root_disk.available_space()) as f64 * 100.0 / root_disk.total_space() as f64;
-    Ok(format!("disk: {:.0}%", used_pct))
+/// The two largest non-zero units of a duration, e.g. `3d 4h`, `2h` or `12m`.
+fn format_span(secs: u64) -> String {
+    let units = [(secs / 86_400, "d"), (secs / 3600 % 24, "h"), (secs / 60 % 60, "m")];
+    let Some(first) = units.iter().position(|&(value, _)| value > 0) else {
+        return "<1m".to_string();
+    };
+    units[first..]
+        .iter()
+        .take(2)
+        .filter(|&&(value, _)| value > 0)
+        .map(|(value, unit)| format!("{}{}", value, unit))
+        .collect::<Vec<_>>()
+        .join(" ")
 }
 
-async fn ram_monitor(sys: Arc<Mutex<System>>) -> Result<String> {
-    let mut sys = sys.lock().unwrap();
-    sys.refresh_memory();
-    let used_pct = sys.used_memory() as f64 * 100.0 / sys.total_memory() as f64;
-    Ok(format!("ram: {:.0}%", used_pct))
+/// Notifies when a disk is fuller than its threshold, at most once per cooldown.
+struct DiskAlert {
+    threshold: f64,
+    cooldown: Duration,
+    enabled: bool,
+    last_sent: Option<Instant>,
 }
 
-async fn read_temp(path: &str) -> Result<String> {
-    let temp_str = fs::read_to_string(path)?;
-    let temp = temp_str.trim().parse::<f32>()? / 1000.0;
-    Ok(format!("{:.0}°C", temp))
+impl DiskAlert {
+    fn new(threshold: f64, cooldown: Duration, enabled: bool) -> Self {
+        DiskAlert { threshold, cooldown, enabled, last_sent: None }
+    }
+
+    /// Returns true when a notification is due for this reading.
+    fn observe(&mut self, used_pct: f64) -> bool {
+        let due = self.enabled && used_pct > self.threshold && self.last_sent.is_none_or(|t| t.elapsed() >= self.cooldown);
+        if due {
+            self.last_sent = Some(Instant::now());
+        }
+        due
+    }
 }
 
-async fn cpu_temp_monitor() -> Result<String> {
-    read_temp("/sys/class/thermal/thermal_zone0/temp").await.map(|t| format!("cpu: {}", t))
+/// Separate alerts for space and inodes, so one filling up doesn't silence the other.
+struct DiskAlerts {
+    space: DiskAlert,
+    inodes: DiskAlert,
 }
-async fn gpu_temp_monitor() -> Result<String> {
-    read_temp("/sys/class/thermal/thermal_zone1/temp").await.map(|t| format!("gpu: {}", t))
+
+/// Starts checking the SMART health of the disk behind `disk`'s mount point, for `disk_monitor`.
+fn spawn_smart_poll(disk: &config::DiskInstance, ctx: &MonitorContext) -> Option<smart::SmartHealth> {
+    let id = ctx.registry.get(&disk.id)?;
+    let Some(device) = smart::device_for(&disk.mount) else {
+        tracing::info!(target: "monitor", module = %id, "No disk found for {}, not checking its SMART health", disk.mount.display());
+        return None;
+    };
+    tracing::info!(target: "monitor", module = %id, "Checking the SMART health of {}", device.display());
+    let health = smart::SmartHealth::default();
+    let interval = Duration::from_secs(ctx.config.disk.smart_interval_secs);
+    tokio::spawn(smart::poll(device, ctx.config.disk.smart_sudo, health.clone(), interval, id, ctx.trigger_tx.clone()));
+    Some(health)
+}
+
+async fn disk_monitor(
+    sys: SystemHandle,
+    mount: PathBuf,
+    label: String,
+    config: Arc<config::DiskConfig>,
+    alerts: Arc<Mutex<DiskAlerts>>,
+    smart: Option<smart::SmartHealth>,
+) -> Result<String> {
+    let display = mount.display().to_string();
+    // Filesystems that allocate inodes dynamically (btrfs) have no inode count.
+    let inodes_pct = inode_used_pct(&mount).unwrap_or_else(|e| {
+        tracing::debug!(target: "monitor", "Cannot read inode usage of {}: {}", mount.display(), e);
+        None
+    });
+    let disk = sys.disk(mount).await?;
+    let used_pct = (disk.total - disk.available) as f64 * 100.0 / disk.total as f64;
+    let (over, notifications) = {
+        let mut alerts = alerts.lock().unwrap();
+        let mut notifications = Vec::new();
+        if alerts.space.observe(used_pct) {
+            let summary = format!("{} is {:.0}% full", display, used_pct);
+            let body = format!("{} free, above the {:.0}% alert threshold", human_bytes(disk.available), alerts.space.threshold);
+            notifications.push((summary, body));
+        }
+        if let Some(pct) = inodes_pct.filter(|&pct| alerts.inodes.observe(pct)) {
+            let summary = format!("{} has used {:.0}% of its inodes", display, pct);
+            let body = format!("Above the {:.0}% alert threshold, new files will fail even with space left", alerts.inodes.threshold);
+            notifications.push((summary, body));
+        }
+        let over = used_pct > alerts.space.threshold || inodes_pct.is_some_and(|pct| pct > alerts.inodes.threshold);
+        (over, notifications)
+    };
+    for (summary, body) in notifications {
+        if let Err(e) = run_command("notify-send", &["-u", "critical", &summary, &body]).await {
+            tracing::warn!("Failed to send disk alert: {}", e);
+        }
+    }
+
+    let marker = if over { "!" } else { "" };
+    let fs_type = if config.show_fs_type { format!("{} ", disk.fs_type) } else { String::new() };
+    let health = smart.and_then(|smart| *smart.lock().unwrap()).map(|health| format!(" {}", health.glyph())).unwrap_or_default();
+    let mut details = Vec::new();
+    if config.show_absolute {
+        details.push(format!("{} free", human_bytes(disk.available)));
+    }
+    if let (true, Some(pct)) = (config.show_inodes, inodes_pct) {
+        details.push(format!("inodes {:.0}%", pct));
+    }
+    if details.is_empty() {
+        return Ok(format!("{}{}: {}{:.0}%{}", marker, label, fs_type, used_pct, health));
+    }
+    Ok(format!("{}{}: {}{:.0}%{} ({})", marker, label, fs_type, used_pct, health, details.join(", ")))
 }
 
-async fn network_monitor() -> Result<String> {
-    run_command("/home/sky/nix-config/bash/network-status.sh", &[]).await
+/// Percentage of inodes in use on the filesystem at `mount`, from `statvfs`. `None` when the
+/// filesystem doesn't report an inode count.
+fn inode_used_pct(mount: &Path) -> Result<Option<f64>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(mount.as_os_str().as_bytes())?;
+    // SAFETY: statvfs only writes into the zeroed struct it is given; `path` is a valid C string.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    if stat.f_files == 0 {
+        return Ok(None);
+    }
+    Ok(Some((stat.f_files - stat.f_ffree) as f64 * 100.0 / stat.f_files as f64))
 }
 
-async fn vpn_monitor() -> Result<String> {
-    if Path::new("/sys/class/net/tun0").exists() {
-        Ok("VPN".to_string())
+/// Formats a byte count with binary units, e.g. `27G`, `1.8T`, `512M`. One decimal below 10.
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if value < 10.0 && unit > 0 {
+        format!("{:.1}{}", value, UNITS[unit])
     } else {
-        Ok(String::new())  // Empty string = hidden from bar
+        format!("{:.0}{}", value, UNITS[unit])
     }
 }
 
-async fn cpu_load_monitor() -> Result<String> {
-    let mut sys = System::new();
-    sys.refresh_cpu();
-    tokio::time::sleep(System::MINIMUM_CPU_UPDATE_INTERVAL).await;
-    sys.refresh_cpu();
-    let usage = sys.global_cpu_info().cpu_usage();
-    Ok(format!("cpu: {:.0}%", usage))
+/// Memory in GiB, or MiB below one GiB, with one decimal that is left out when it is zero,
+/// e.g. `12.4G` or `32G`.
+fn memory_size(bytes: u64) -> String {
+    const MIB: f64 = 1024.0 * 1024.0;
+    const GIB: f64 = 1024.0 * MIB;
+    let (value, unit) = if bytes as f64 >= GIB { (bytes as f64 / GIB, "G") } else { (bytes as f64 / MIB, "M") };
+    let text = format!("{:.1}", value);
+    format!("{}{}", text.strip_suffix(".0").unwrap_or(&text), unit)
+}
+
+async fn ram_monitor(sys: SystemHandle, format: RamFormat, usage: RamUsage, vram: Option<Arc<vram::Vram>>) -> Result<String> {
+    let mem = sys.memory().await?;
+    let used = match usage {
+        RamUsage::App => mem.used,
+        RamUsage::Total => mem.total - mem.free,
+    };
+    let used_pct = used as f64 * 100.0 / mem.total as f64;
+    let mut text = match format {
+        RamFormat::Percent => format!("ram: {:.0}%", used_pct),
+        RamFormat::Absolute => format!("ram: {} / {}", memory_size(used), memory_size(mem.total)),
+        RamFormat::Both => format!("ram: {} ({:.0}%)", memory_size(used), used_pct),
+    };
+    // A failing GPU query shouldn't take system memory off the bar.
+    if let Some(vram) = vram {
+        match vram.usage().await {
+            Ok((used, total)) => text.push_str(&format!(" | vram: {}/{}", human_bytes(used), human_bytes(total))),
+            Err(e) => tracing::debug!(target: "monitor::ram", "Cannot read VRAM usage: {:#}", e),
+        }
+    }
+    Ok(text)
+}
+
+async fn hog_monitor(sys: SystemHandle, name_width: usize) -> Result<String> {
+    let hogs = sys.hogs().await?;
+    let cpu = hogs.cpu.map(|(name, usage)| format!("{} {:.0}%", util::truncate(&name, name_width), usage));
+    let memory = hogs.memory.map(|(name, bytes)| format!("{} {}", util::truncate(&name, name_width), human_bytes(bytes)));
+    Ok(format!("top: {} / {}", cpu.as_deref().unwrap_or("-"), memory.as_deref().unwrap_or("-")))
+}
+
+#[cfg(target_os = "linux")]
+async fn read_temp(path: &Path) -> Result<f32> {
+    let temp_str = fs::read_to_string(path)?;
+    Ok(temp_str.trim().parse::<f32>()? / 1000.0)
 }
 
-async fn battery_monitor() -> Result<String> {
-    // Requires `acpi` to be installed
-    let acpi_output = run_command("acpi", &["-b"]).await?;
-    let charge_threshold_output = run_command("cat", &["/sys/class/power_supply/BAT0/charge_stop_threshold"]).await?;
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum AlertState {
+    /// At or below the threshold, or cooled down past the hysteresis margin.
+    Below,
+    /// Above the threshold, but no notification sent yet (a single spike, or still in cooldown).
+    Above,
+    /// Above the threshold and notified; an all-clear follows once it cools down.
+    Notified,
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, PartialEq)]
+enum AlertEvent {
+    Critical,
+    AllClear,
+}
+
+#[cfg(target_os = "linux")]
+/// The alert state machine shared by the temperature modules. It decides when to notify and
+/// whether the reading should be marked in the bar.
+struct TempAlert {
+    label: String,
+    threshold: f32,
+    hysteresis: f32,
+    cooldown: Duration,
+    enabled: bool,
+    state: AlertState,
+    consecutive: u32,
+    last_sent: Option<Instant>,
+}
+
+#[cfg(target_os = "linux")]
+impl TempAlert {
+    fn new(label: String, config: &config::TempConfig, default_threshold: f32, enabled: bool) -> Self {
+        TempAlert {
+            label,
+            threshold: config.alert_threshold.unwrap_or(default_threshold),
+            hysteresis: config.alert_hysteresis.unwrap_or(5.0),
+            cooldown: Duration::from_secs(config.alert_cooldown.unwrap_or(300)),
+            enabled,
+            state: AlertState::Below,
+            consecutive: 0,
+            last_sent: None,
+        }
+    }
+
+    /// Records a reading and returns the notification it calls for, if any. A critical
+    /// notification needs more than one reading in a row above the threshold (so transient spikes
+    /// are ignored) and at most one is sent per cooldown, so a sensor hovering around the threshold
+    /// can't flood the desktop. The all-clear follows once the reading drops below
+    /// `threshold - hysteresis`. `now` is the time of the reading.
+    fn observe(&mut self, temp: f32, now: Instant) -> Option<AlertEvent> {
+        if temp <= self.threshold - self.hysteresis {
+            let previous = std::mem::replace(&mut self.state, AlertState::Below);
+            self.consecutive = 0;
+            return (previous == AlertState::Notified && self.enabled).then_some(AlertEvent::AllClear);
+        }
+        if temp <= self.threshold {
+            // Within the hysteresis margin: keep the current state, but restart the spike filter.
+            self.consecutive = 0;
+            return None;
+        }
+        self.consecutive += 1;
+        match self.state {
+            AlertState::Notified => None,
+            AlertState::Below | AlertState::Above => {
+                self.state = AlertState::Above;
+                let cooled_down = self.last_sent.is_none_or(|t| now.duration_since(t) >= self.cooldown);
+                if self.enabled && self.consecutive >= 2 && cooled_down {
+                    self.state = AlertState::Notified;
+                    self.last_sent = Some(now);
+                    Some(AlertEvent::Critical)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    /// Whether the reading should be marked as critical in the bar.
+    fn critical(&self) -> bool {
+        self.state != AlertState::Below
+    }
+}
 
-    let re = Regex::new(r"Battery 0: ([\w\s]+), (\d+)%")?;
-    if let Some(caps) = re.captures(&acpi_output) {
-        let status = &caps[1];
-        let percent = &caps[2];
-        let status_char = match status {
-            "Charging" => "C",
-            "Discharging" => "D",
-            "Full" => "F",
-            _ => "?",
+#[cfg(target_os = "linux")]
+async fn temp_monitor(path: &Path, prefix: &str, alert: Arc<Mutex<TempAlert>>) -> Result<String> {
+    let temp = read_temp(path).await?;
+    let (event, critical, label, threshold) = {
+        let mut alert = alert.lock().unwrap();
+        let event = alert.observe(temp, Instant::now());
+        (event, alert.critical(), alert.label.clone(), alert.threshold)
+    };
+    if let Some(event) = event {
+        let summary = format!("{} temperature {:.0}°C", label, temp);
+        let (urgency, body) = match event {
+            AlertEvent::Critical => ("critical", format!("Above the {:.0}°C alert threshold", threshold)),
+            AlertEvent::AllClear => ("normal", format!("Back below the {:.0}°C alert threshold", threshold)),
         };
-        Ok(format!("bat: {}/{}% {}", percent, charge_threshold_output, status_char))
+        if let Err(e) = run_command("notify-send", &["-u", urgency, &summary, &body]).await {
+            tracing::warn!("Failed to send temperature alert: {}", e);
+        }
+    }
+    let marker = if critical { "!" } else { "" };
+    Ok(format!("{}{}: {:.0}°C", marker, prefix, temp))
+}
+
+/// Busy percentages below this count as an idle GPU.
+#[cfg(target_os = "linux")]
+const GPU_BUSY_THRESHOLD: u32 = 10;
+
+/// True until no GPU has been busy for `idle_after`, checked whenever the module is about to run.
+/// Without a readable `gpu_busy_percent` (only amdgpu has one) the GPU always counts as busy.
+#[cfg(target_os = "linux")]
+fn gpu_busy_condition(idle_after: Duration) -> Condition {
+    let last_busy = Mutex::new(Instant::now());
+    Arc::new(move || {
+        let mut last_busy = last_busy.lock().unwrap();
+        if gpu_busy_percent().is_none_or(|busy| busy >= GPU_BUSY_THRESHOLD) {
+            *last_busy = Instant::now();
+        }
+        last_busy.elapsed() < idle_after
+    })
+}
+
+/// The highest `gpu_busy_percent` of any DRM card.
+#[cfg(target_os = "linux")]
+fn gpu_busy_percent() -> Option<u32> {
+    fs::read_dir("/sys/class/drm")
+        .ok()?
+        .flatten()
+        .filter_map(|card| fs::read_to_string(card.path().join("device/gpu_busy_percent")).ok()?.trim().parse().ok())
+        .max()
+}
+
+/// How often the first core has been thermally throttled since boot. Intel only.
+#[cfg(target_os = "linux")]
+const THROTTLE_COUNT: &str = "/sys/devices/system/cpu/cpu0/thermal_throttle/core_throttle_count";
+
+#[cfg(target_os = "linux")]
+fn throttle_count() -> Option<u64> {
+    fs::read_to_string(THROTTLE_COUNT).ok()?.trim().parse().ok()
+}
+
+/// Appends `(THR)` when the CPU was throttled since the previous run, e.g. `cpu: 89°C (THR)`.
+/// `throttles` holds the count seen by that run.
+#[cfg(target_os = "linux")]
+async fn cpu_temp_monitor(alert: Arc<Mutex<TempAlert>>, throttles: Arc<std::sync::atomic::AtomicU64>) -> Result<String> {
+    let temp = temp_monitor(Path::new("/sys/class/thermal/thermal_zone0/temp"), "cpu", alert).await?;
+    let Some(count) = throttle_count() else {
+        return Ok(temp);
+    };
+    if count > throttles.swap(count, Ordering::Relaxed) {
+        return Ok(format!("{} (THR)", temp));
+    }
+    Ok(temp)
+}
+
+/// Shows the network status script's output when it is installed, otherwise the active
+/// connection as detected from sysfs. `wan_ip` is the external address kept by `net::poll_wan_ip`.
+async fn network_monitor(config: Arc<config::NetConfig>, wan_ip: Option<net::WanIp>) -> Result<String> {
+    let mut status = if Path::new(NETWORK_SCRIPT).exists() {
+        let status = run_command_clean(NETWORK_SCRIPT, &[]).await?;
+        tracing::debug!(target: "monitor::net", %status, "Ran network status script");
+        status
     } else {
-        Ok("bat: N/A".to_string())
-    }
-}
-
-async fn bluetooth_monitor() -> Result<String> {
-    // Requires `bluetoothctl`
-    let cmd = r#"
-        CONNECTED_MAC=$(bluetoothctl devices Connected | cut -d' ' -f2)
-        if [ -n "$CONNECTED_MAC" ]; then
-            INFO=$(bluetoothctl info $CONNECTED_MAC)
-            NAME=$(echo "$INFO" | grep "Name:" | cut -d' ' -f2-)
-            BATTERY=$(echo "$INFO" | grep "Battery Percentage" | sed -n 's/.*(\(.*\))/\1/p')
-            if [ -n "$BATTERY" ]; then
-                echo "bt: $NAME ${BATTERY}%"
-            else
-                echo "bt: $NAME"
-            fi
-        fi
-    "#;
-    run_command("bash", &["-c", cmd]).await
-}
-
-async fn volume_monitor() -> Result<String> {
-    // Requires `amixer` from alsa-utils
-    let cmd = "amixer sget Master | awk -F'[][]' '/Front Left:/ { print $2 }'";
-    let volume = run_command("bash", &["-c", cmd]).await?;
-    Ok(format!("vol: {}", volume))
-}
-
-async fn notification_monitor() -> Result<String> {
-    let is_paused = run_command("dunstctl", &["is-paused"]).await?;
-    if is_paused.trim() == "true" {
-        Ok("n: disabled".to_string())
+        net::connection_status(&config).await?
+    };
+    // Offline there is no address to show, which isn't an error for the module.
+    if config.show_ip {
+        match net::ip_suffix(&config).await {
+            Ok(ips) if !ips.is_empty() => status = format!("{} {}", status, ips),
+            Ok(_) => {}
+            Err(e) => tracing::debug!(target: "monitor::net", "No address to show: {:#}", e),
+        }
+    }
+    if let Some(ip) = wan_ip.and_then(|wan_ip| *wan_ip.lock().unwrap()) {
+        status = format!("{} | WAN: {}", status, ip);
+    }
+    Ok(status)
+}
+
+/// With the Mullvad CLI installed, Mullvad's own state comes first, which also shows when its
+/// kill switch is blocking traffic. Other VPNs are detected by their tun0 interface.
+#[cfg(target_os = "linux")]
+async fn vpn_monitor(check_routing: bool, mullvad: bool) -> Result<String> {
+    if mullvad {
+        let status = mullvad_status(&run_command("mullvad", &["status"]).await?);
+        if !status.is_empty() {
+            return Ok(status);
+        }
+    }
+    if !Path::new("/sys/class/net/tun0").exists() {
+        return Ok(String::new());  // Empty string = hidden from bar
+    }
+    if check_routing && !routes_all_traffic(&fs::read_to_string("/proc/net/route")?, "tun0") {
+        return Ok("vpn: split".to_string());
+    }
+    Ok("VPN".to_string())
+}
+
+/// Turns `mullvad status` into `vpn: mullvad SE`, `vpn: mullvad blocked` or
+/// `vpn: mullvad connecting`, and nothing when disconnected. Accepts both the current layout
+/// (`Connected` with a `Relay: se-got-wg-001` line below) and the older single line
+/// `Tunnel status: Connected to WireGuard se-got-wg-001 in Gothenburg, Sweden`.
+#[cfg(target_os = "linux")]
+fn mullvad_status(output: &str) -> String {
+    let first = output.lines().next().unwrap_or_default().trim();
+    let state = first.strip_prefix("Tunnel status:").unwrap_or(first).trim_start();
+    if state.starts_with("Connected") {
+        // Relay hostnames start with the country code.
+        let country = output.split_whitespace().find_map(|word| {
+            let (code, rest) = word.split_once('-')?;
+            (code.len() == 2 && code.bytes().all(|b| b.is_ascii_lowercase()) && rest.contains('-')).then(|| code.to_ascii_uppercase())
+        });
+        match country {
+            Some(code) => format!("vpn: mullvad {}", code),
+            None => "vpn: mullvad".to_string(),
+        }
+    } else if state.starts_with("Blocked") {
+        "vpn: mullvad blocked".to_string()
+    } else if state.starts_with("Connecting") {
+        "vpn: mullvad connecting".to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Whether `/proc/net/route` sends all IPv4 traffic through `iface`: either the default route, or
+/// the `0.0.0.0/1` + `128.0.0.0/1` pair that OpenVPN and WireGuard tools use to override it.
+#[cfg(target_os = "linux")]
+fn routes_all_traffic(route_table: &str, iface: &str) -> bool {
+    // Destination and mask are little-endian hex; 0x80 in the first byte is 128.0.0.0 and the /1 mask.
+    let via_iface: Vec<(&str, &str)> = route_table
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            (fields.len() >= 8 && fields[0] == iface).then(|| (fields[1], fields[7]))
+        })
+        .collect();
+    let has = |dest: &str, mask: &str| via_iface.iter().any(|&(d, m)| d.eq_ignore_ascii_case(dest) && m.eq_ignore_ascii_case(mask));
+    has("00000000", "00000000") || (has("00000000", "00000080") && has("00000080", "00000080"))
+}
+
+/// Readings shown in the cpu_load sparkline.
+const CPU_SPARKLINE_LEN: usize = 10;
+
+/// Shows CPU usage, followed by a sparkline of the last readings when `history` is kept.
+async fn cpu_load_monitor(sys: SystemHandle, history: Option<Arc<Mutex<VecDeque<f32>>>>) -> Result<String> {
+    let usage = sys.cpu().await?;
+    let Some(history) = history else {
+        return Ok(format!("cpu: {:.0}%", usage));
+    };
+    let mut history = history.lock().unwrap();
+    if history.len() == CPU_SPARKLINE_LEN {
+        history.pop_front();
+    }
+    history.push_back(usage);
+    Ok(format!("cpu: {:.0}% {}", usage, util::sparkline(history.make_contiguous(), 0.0, 100.0)))
+}
+
+/// How often `/sys/class/power_supply` is rescanned. sysfs doesn't report devices coming and
+/// going through inotify, so it is polled.
+#[cfg(target_os = "linux")]
+const HOTPLUG_SCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Runs the battery module only while a battery is present, so a battery attached after startup
+/// (or a dock's) shows up, and an unplugged one leaves the bar instead of showing `bat: N/A`.
+#[cfg(target_os = "linux")]
+async fn battery_hotplug(ctx: MonitorContext, interval: Duration) {
+    let Some(id) = ctx.registry.get("battery") else { return };
+    let (show_bar, has_acpi) = (ctx.config.battery.show_bar, command_exists("acpi"));
+    let mut running = false;
+    let mut scan = tokio::time::interval(HOTPLUG_SCAN_INTERVAL);
+    loop {
+        scan.tick().await;
+        let present = has_battery();
+        if present == running {
+            continue;
+        }
+        running = present;
+        if present {
+            tracing::info!(target: "monitor", "Battery detected, starting the battery module");
+            spawn_monitor(&id, interval, move || battery_monitor(show_bar, has_acpi), &ctx);
+        } else {
+            tracing::info!(target: "monitor", "Battery removed, stopping the battery module");
+            ctx.supervisor.stop(id.clone());
+        }
+    }
+}
+
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// The first battery under `/sys/class/power_supply`, usually `BAT0`.
+#[cfg(target_os = "linux")]
+fn battery_dir() -> Option<PathBuf> {
+    let mut batteries: Vec<PathBuf> = fs::read_dir(POWER_SUPPLY_DIR)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| fs::read_to_string(path.join("type")).is_ok_and(|t| t.trim() == "Battery"))
+        .collect();
+    batteries.sort();
+    batteries.into_iter().next()
+}
+
+#[cfg(target_os = "linux")]
+fn has_battery() -> bool {
+    battery_dir().is_some()
+}
+
+/// Shows `bat: 82/80% D`: charge, the charge limit and the state. Uses `acpi` when installed and
+/// otherwise reads the same values from sysfs, with identical output.
+#[cfg(target_os = "linux")]
+async fn battery_monitor(show_bar: bool, has_acpi: bool) -> Result<String> {
+    let dir = battery_dir();
+    // Without a configured limit the battery charges to 100%.
+    let threshold = dir
+        .iter()
+        .flat_map(|dir| ["charge_control_end_threshold", "charge_stop_threshold"].map(|name| dir.join(name)))
+        .find_map(|path| fs::read_to_string(path).ok())
+        .map_or_else(|| "100".to_string(), |t| t.trim().to_string());
+
+    let reading = if has_acpi {
+        let acpi_output = run_command("acpi", &["-b"]).await?;
+        tracing::debug!(target: "monitor::battery", %acpi_output, %threshold, "Read battery state from acpi");
+        let re = regex::Regex::new(r"Battery 0: ([\w\s]+), (\d+)%")?;
+        re.captures(&acpi_output).map(|caps| (caps[1].to_string(), caps[2].to_string()))
     } else {
-        Ok(String::new())
+        let read = |name: &str| dir.as_ref().and_then(|dir| fs::read_to_string(dir.join(name)).ok()).map(|v| v.trim().to_string());
+        let reading = read("status").zip(read("capacity"));
+        tracing::debug!(target: "monitor::battery", ?reading, %threshold, "Read battery state from sysfs");
+        reading
+    };
+    let Some((status, percent)) = reading else {
+        tracing::debug!(target: "monitor::battery", "Unrecognised battery state");
+        return Ok("bat: N/A".to_string());
+    };
+    let status_char = match status.as_str() {
+        "Charging" => "C",
+        "Discharging" => "D",
+        "Full" => "F",
+        _ => "?",
+    };
+    let bar = match percent.parse() {
+        Ok(pct) if show_bar => format!("{} ", util::percent_to_bar(pct, 5)),
+        _ => String::new(),
+    };
+    Ok(format!("bat: {}{}/{}% {}", bar, percent, threshold, status_char))
+}
+
+async fn idle_monitor(has_xset: bool, has_inhibit: bool) -> Result<String> {
+    // Shows a coffee cup while something keeps the screen awake; hidden otherwise.
+    let screen_awake = has_xset && run_command("xset", &["q"]).await.map(|out| xset_keeps_screen_on(&out)).unwrap_or(false);
+    let inhibited = !screen_awake
+        && has_inhibit
+        && run_command("systemd-inhibit", &["--list", "--no-legend"]).await.map(|out| has_idle_inhibitor(&out)).unwrap_or(false);
+    tracing::debug!(target: "monitor::idle", screen_awake, inhibited, "Checked idle inhibitors");
+    Ok(if screen_awake || inhibited { "☕".to_string() } else { String::new() })
+}
+
+/// True when `xset q` reports both the screensaver and DPMS switched off, i.e. the screen never blanks.
+fn xset_keeps_screen_on(output: &str) -> bool {
+    let saver_off = output
+        .lines()
+        .find_map(|l| l.trim().strip_prefix("timeout:"))
+        .and_then(|rest| rest.split_whitespace().next())
+        .is_some_and(|t| t == "0");
+    let dpms_on = output.contains("DPMS is Enabled");
+    saver_off && !dpms_on
+}
+
+/// True when `systemd-inhibit --list --no-legend` lists a blocking idle or sleep inhibitor.
+fn has_idle_inhibitor(output: &str) -> bool {
+    output.lines().any(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        fields.last() == Some(&"block") && fields.iter().any(|f| f.split(':').any(|what| what == "idle" || what == "sleep"))
+    })
+}
+
+async fn privacy_monitor() -> Result<String> {
+    let dump = run_command("pw-dump", &[]).await?;
+    let (mic, screen) = active_captures(&dump)?;
+    tracing::debug!(target: "monitor::privacy", mic, screen, "Inspected PipeWire nodes");
+    let parts: Vec<&str> = [(mic, "🎙 REC"), (screen, "🖵 REC")].into_iter().filter(|(on, _)| *on).map(|(_, s)| s).collect();
+    Ok(parts.join(" "))
+}
+
+/// Returns whether a running node in `pw-dump` output is capturing the microphone and/or the screen.
+/// Monitor and loopback streams (peak meters, sink-to-source loopbacks) are not capture.
+fn active_captures(dump: &str) -> Result<(bool, bool)> {
+    let objects: Vec<serde_json::Value> = serde_json::from_str(dump)?;
+    let (mut mic, mut screen) = (false, false);
+    for object in &objects {
+        if object["type"] != "PipeWire:Interface:Node" || object["info"]["state"] != "running" {
+            continue;
+        }
+        let props = &object["info"]["props"];
+        let prop = |key: &str| props[key].as_str().unwrap_or_default();
+        let flag = |key: &str| props[key].as_bool().unwrap_or(false) || props[key] == "true";
+        match prop("media.class") {
+            "Stream/Input/Audio" => {
+                // Recording a sink's `.monitor` source captures playback, not the microphone.
+                let loopback = flag("stream.monitor")
+                    || flag("stream.capture.sink")
+                    || prop("target.object").ends_with(".monitor")
+                    || prop("node.name").contains("loopback");
+                mic |= !loopback;
+            }
+            "Video/Source" | "Stream/Input/Video" => {
+                screen |= prop("media.role") == "Screen" || prop("node.name").contains("xdg-desktop-portal");
+            }
+            _ => {}
+        }
+    }
+    Ok((mic, screen))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+    const GIB: u64 = 1024 * MIB;
+
+    #[test]
+    fn memory_sizes_scale_to_gib_or_mib() {
+        assert_eq!(memory_size(12 * GIB + 400 * MIB), "12.4G");
+        assert_eq!(memory_size(32 * GIB), "32G");
+        assert_eq!(memory_size(GIB), "1G");
+        assert_eq!(memory_size(512 * MIB), "512M");
+        assert_eq!(memory_size(1536 * MIB / 10), "153.6M");
+        assert_eq!(memory_size(0), "0M");
+    }
+
+    /// Thursday 15 October 2026, 09:05:00, in `locale`'s names.
+    fn clock_in(locale: Option<&str>) -> String {
+        let time = chrono::NaiveDate::from_ymd_opt(2026, 10, 15).unwrap().and_hms_opt(9, 5, 0).unwrap();
+        time.and_utc().format_localized(config::DEFAULT_DATETIME_FORMAT, datetime_locale(locale)).to_string()
+    }
+
+    #[test]
+    fn datetime_in_german_and_french() {
+        assert_eq!(clock_in(Some("de_DE.UTF-8")), "Do 15 Okt 09:05:00");
+        assert_eq!(clock_in(Some("fr_FR")), "jeu. 15 oct. 09:05:00");
+        assert_eq!(clock_in(Some("fr_FR@euro")), clock_in(Some("fr_FR")));
+    }
+
+    #[test]
+    fn datetime_falls_back_to_english() {
+        assert_eq!(clock_in(Some("C")), "Thu 15 Oct 09:05:00");
+        assert_eq!(clock_in(Some("POSIX")), "Thu 15 Oct 09:05:00");
+        assert_eq!(clock_in(Some("C.UTF-8")), "Thu 15 Oct 09:05:00");
+        assert_eq!(clock_in(Some("xx_YY.UTF-8")), "Thu 15 Oct 09:05:00");
+    }
+
+    #[cfg(target_os = "linux")]
+    fn temp_alert() -> TempAlert {
+        // Threshold 90°C, hysteresis 5°C, cooldown 300s.
+        TempAlert::new("CPU".to_string(), &config::TempConfig::default(), 90.0, true)
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn temp_alert_ignores_a_single_spike() {
+        let (mut alert, now) = (temp_alert(), Instant::now());
+        assert_eq!(alert.observe(95.0, now), None);
+        assert!(alert.critical());
+        assert_eq!(alert.observe(80.0, now), None);
+        assert!(!alert.critical());
+        assert_eq!(alert.observe(95.0, now), None);
+        assert_eq!(alert.observe(96.0, now), Some(AlertEvent::Critical));
+        // Staying hot doesn't notify again.
+        assert_eq!(alert.observe(97.0, now), None);
+        assert!(alert.critical());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn temp_alert_clears_only_past_the_hysteresis() {
+        let (mut alert, now) = (temp_alert(), Instant::now());
+        alert.observe(95.0, now);
+        assert_eq!(alert.observe(95.0, now), Some(AlertEvent::Critical));
+        // Below the threshold but within 5°C of it: still alerting, and no re-arm.
+        assert_eq!(alert.observe(88.0, now), None);
+        assert!(alert.critical());
+        assert_eq!(alert.observe(91.0, now), None);
+        assert_eq!(alert.observe(92.0, now), None);
+        assert_eq!(alert.observe(85.0, now), Some(AlertEvent::AllClear));
+        assert!(!alert.critical());
+        assert_eq!(alert.observe(80.0, now), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn temp_alert_waits_out_the_cooldown() {
+        let (mut alert, start) = (temp_alert(), Instant::now());
+        alert.observe(95.0, start);
+        assert_eq!(alert.observe(95.0, start), Some(AlertEvent::Critical));
+        assert_eq!(alert.observe(70.0, start + Duration::from_secs(10)), Some(AlertEvent::AllClear));
+        // Hot again within the cooldown: marked in the bar, but not notified.
+        let soon = start + Duration::from_secs(60);
+        assert_eq!(alert.observe(95.0, soon), None);
+        assert_eq!(alert.observe(95.0, soon), None);
+        assert!(alert.critical());
+        let later = start + Duration::from_secs(300);
+        assert_eq!(alert.observe(95.0, later), Some(AlertEvent::Critical));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn temp_alert_disabled_never_notifies() {
+        let mut alert = TempAlert::new("GPU".to_string(), &config::TempConfig::default(), 85.0, false);
+        let now = Instant::now();
+        assert_eq!(alert.observe(90.0, now), None);
+        assert_eq!(alert.observe(90.0, now), None);
+        assert!(alert.critical());
+        assert_eq!(alert.observe(70.0, now), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn mullvad_connected_shows_the_relay_country() {
+        let current = "Connected\n    Relay:                  se-got-wg-001\n    Features:               Quantum Resistance\n    Visible location:       Sweden, Gothenburg. IPv4: 185.213.154.68\n";
+        assert_eq!(mullvad_status(current), "vpn: mullvad SE");
+        let older = "Tunnel status: Connected to WireGuard de-fra-wg-102 in Frankfurt, Germany\n";
+        assert_eq!(mullvad_status(older), "vpn: mullvad DE");
+        assert_eq!(mullvad_status("Connected\n"), "vpn: mullvad");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn mullvad_connecting_blocked_and_disconnected() {
+        assert_eq!(mullvad_status("Connecting\n    Relay:                  us-nyc-wg-301\n"), "vpn: mullvad connecting");
+        assert_eq!(mullvad_status("Tunnel status: Connecting to WireGuard us-nyc-wg-301\n"), "vpn: mullvad connecting");
+        assert_eq!(mullvad_status("Blocked: No relay servers match the current settings\n"), "vpn: mullvad blocked");
+        assert_eq!(mullvad_status("Disconnected\n"), "");
+        assert_eq!(mullvad_status("Tunnel status: Disconnected\n"), "");
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn mullvad_unrecognized_output_is_hidden() {
+        assert_eq!(mullvad_status("Error: Management RPC server or client error\n"), "");
+        assert_eq!(mullvad_status(""), "");
+    }
+
+    #[cfg(target_os = "linux")]
+    const ROUTE_HEADER: &str = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT\n";
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn full_tunnel_default_route() {
+        let table = format!(
+            "{}tun0\t00000000\t00000000\t0001\t0\t0\t0\t00000000\t0\t0\t0\n\
+             eth0\t0002A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0\n",
+            ROUTE_HEADER
+        );
+        assert!(routes_all_traffic(&table, "tun0"));
+        assert!(!routes_all_traffic(&table, "eth0"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn split_halves_override_the_default_route() {
+        let pair = format!(
+            "{}eth0\t00000000\t0102A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n\
+             tun0\t00000000\t0100080A\t0003\t0\t0\t0\t00000080\t0\t0\t0\n\
+             tun0\t00000080\t0100080A\t0003\t0\t0\t0\t00000080\t0\t0\t0\n",
+            ROUTE_HEADER
+        );
+        assert!(routes_all_traffic(&pair, "tun0"));
+        // Only one half, plus a route to the VPN's own network: split tunnel.
+        let half = format!(
+            "{}eth0\t00000000\t0102A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n\
+             tun0\t00000000\t0100080A\t0003\t0\t0\t0\t00000080\t0\t0\t0\n\
+             tun0\t0000080A\t00000000\t0001\t0\t0\t0\t0000FFFF\t0\t0\t0\n",
+            ROUTE_HEADER
+        );
+        assert!(!routes_all_traffic(&half, "tun0"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn absent_interface_routes_nothing() {
+        let table = format!("{}eth0\t00000000\t0102A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0\n", ROUTE_HEADER);
+        assert!(!routes_all_traffic(&table, "tun0"));
+        assert!(!routes_all_traffic(ROUTE_HEADER, "tun0"));
+    }
+
+    fn xset_q(saver_timeout: u32, dpms: &str) -> String {
+        format!(
+            "Keyboard Control:\n  auto repeat:  on    key click percent:  0    LED mask:  00000000\n\
+             Screen Saver:\n  prefer blanking:  yes    allow exposures:  yes\n  timeout:  {}    cycle:  600\n\
+             DPMS (Energy Star):\n  Standby: 600    Suspend: 600    Off: 600\n  DPMS is {}\n",
+            saver_timeout, dpms
+        )
+    }
+
+    #[test]
+    fn xset_screen_kept_on_only_without_saver_and_dpms() {
+        assert!(xset_keeps_screen_on(&xset_q(0, "Disabled")));
+        assert!(!xset_keeps_screen_on(&xset_q(0, "Enabled")));
+        assert!(!xset_keeps_screen_on(&xset_q(600, "Disabled")));
+        assert!(!xset_keeps_screen_on(&xset_q(600, "Enabled")));
+        assert!(!xset_keeps_screen_on(""));
+    }
+
+    const INHIBIT_SLEEP_DELAY: &str = "NetworkManager 0    root 812  NetworkManager  sleep                NetworkManager needs to turn off networks delay\n\
+         UPower         0    root 1022 upowerd         sleep                Pause device polling                       delay\n";
+
+    #[test]
+    fn idle_block_inhibits() {
+        let list = format!("{}firefox        1000 ana  4210 firefox         idle                 video-playing                              block\n", INHIBIT_SLEEP_DELAY);
+        assert!(has_idle_inhibitor(&list));
+        let caffeine = "caffeine       1000 ana  5120 caffeine        idle:sleep           Caffeine is enabled                        block\n";
+        assert!(has_idle_inhibitor(caffeine));
+    }
+
+    #[test]
+    fn delay_and_unrelated_blocks_do_not_inhibit() {
+        assert!(!has_idle_inhibitor(INHIBIT_SLEEP_DELAY));
+        let lid = format!("{}gnome-session  1000 ana  1433 gsd-power       handle-lid-switch    External monitor attached                  block\n", INHIBIT_SLEEP_DELAY);
+        assert!(!has_idle_inhibitor(&lid));
+        assert!(!has_idle_inhibitor(""));
+    }
+
+    /// A `pw-dump` node object.
+    fn pw_node(state: &str, props: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({"id": 70, "type": "PipeWire:Interface:Node", "info": {"state": state, "props": props}})
+    }
+
+    fn captures(nodes: &[serde_json::Value]) -> (bool, bool) {
+        active_captures(&serde_json::Value::from(nodes.to_vec()).to_string()).unwrap()
+    }
+
+    #[test]
+    fn microphone_recording_counts() {
+        let firefox = pw_node("running", serde_json::json!({"media.class": "Stream/Input/Audio", "node.name": "Firefox", "application.name": "Firefox"}));
+        assert_eq!(captures(std::slice::from_ref(&firefox)), (true, false));
+        // An idle stream isn't recording.
+        let mut idle = firefox;
+        idle["info"]["state"] = "idle".into();
+        assert_eq!(captures(&[idle]), (false, false));
+    }
+
+    #[test]
+    fn monitor_sources_are_not_microphone_capture() {
+        let nodes = [
+            // The sink's own monitor source.
+            pw_node("running", serde_json::json!({"media.class": "Audio/Source", "node.name": "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor"})),
+            // OBS recording desktop audio from that monitor source.
+            pw_node("running", serde_json::json!({"media.class": "Stream/Input/Audio", "node.name": "OBS", "target.object": "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor"})),
+            // pavucontrol's peak meter.
+            pw_node("running", serde_json::json!({"media.class": "Stream/Input/Audio", "node.name": "PulseAudio Volume Control", "stream.monitor": true})),
+            pw_node("running", serde_json::json!({"media.class": "Stream/Input/Audio", "node.name": "output.loopback-1234", "stream.capture.sink": "true"})),
+        ];
+        assert_eq!(captures(&nodes), (false, false));
+    }
+
+    #[test]
+    fn screen_sharing_counts() {
+        let portal = pw_node("running", serde_json::json!({"media.class": "Video/Source", "node.name": "xdg-desktop-portal-wlr"}));
+        let webcam = pw_node("running", serde_json::json!({"media.class": "Video/Source", "node.name": "v4l2_input.usb-Webcam", "media.role": "Camera"}));
+        assert_eq!(captures(&[portal]), (false, true));
+        assert_eq!(captures(&[webcam]), (false, false));
+        assert!(active_captures("not json").is_err());
+    }
+
+    #[test]
+    fn spans_show_the_two_largest_units() {
+        assert_eq!(format_span(3 * 86_400 + 4 * 3600 + 59 * 60), "3d 4h");
+        assert_eq!(format_span(86_400 + 30 * 60), "1d");
+        assert_eq!(format_span(2 * 3600), "2h");
+        assert_eq!(format_span(2 * 3600 + 5 * 60 + 59), "2h 5m");
+        assert_eq!(format_span(12 * 60 + 30), "12m");
+        assert_eq!(format_span(59), "<1m");
+        assert_eq!(format_span(0), "<1m");
+    }
+
+    fn countdown(label: Option<&str>) -> Arc<Countdown> {
+        Countdown::new("2026-12-24T18:00:00+01:00", label.map(str::to_string)).unwrap()
+    }
+
+    fn at(time: &str) -> chrono::DateTime<chrono::Utc> {
+        chrono::DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&chrono::Utc)
+    }
+
+    #[test]
+    fn countdown_before_the_target() {
+        let vacation = countdown(Some("vacation"));
+        assert_eq!(vacation.render(at("2026-12-21T14:00:00+01:00")), "3d 4h to vacation");
+        assert_eq!(vacation.render(at("2026-12-24T17:48:00+01:00")), "12m to vacation");
+        assert_eq!(countdown(None).render(at("2026-12-24T16:00:00+01:00")), "2h");
+    }
+
+    #[test]
+    fn countdown_crosses_zero() {
+        let vacation = countdown(Some("vacation"));
+        assert_eq!(vacation.render(at("2026-12-24T17:59:30+01:00")), "<1m to vacation");
+        assert_eq!(vacation.render(at("2026-12-24T18:00:00+01:00")), "<1m to vacation");
+        assert_eq!(vacation.render(at("2026-12-24T18:00:30+01:00")), "vacation was <1m ago");
+        assert_eq!(countdown(None).render(at("2026-12-24T18:00:30+01:00")), "<1m ago");
+    }
+
+    #[test]
+    fn countdown_after_the_target() {
+        assert_eq!(countdown(Some("vacation")).render(at("2026-12-24T20:00:00+01:00")), "vacation was 2h ago");
+        assert_eq!(countdown(None).render(at("2027-01-02T19:30:00+01:00")), "9d 1h ago");
+        assert!(Countdown::new("next friday", None).is_none());
     }
 }
@@ -0,0 +1,96 @@
+use crate::bar_state::BarState;
+use crate::registry::ModuleId;
+use crate::stats::{Stats, DURATION_BUCKETS};
+use anyhow::Result;
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A client gets this long to send its request and take the answer.
+const CONNECTION_TIMEOUT: Duration = Duration::from_secs(5);
+/// Pause after a failed accept, e.g. when out of file descriptors, so the loop doesn't spin.
+const ACCEPT_RETRY: Duration = Duration::from_millis(100);
+
+/// Serves `/metrics` in the Prometheus text format on localhost. This is deliberately a bare
+/// HTTP/1.0-style responder: one request per connection, no keep-alive. Each connection is
+/// answered in its own task, so a slow client doesn't hold up the next scrape.
+pub async fn serve(port: u16, results: Arc<Mutex<BarState>>, stats: Stats) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    tracing::info!("Serving metrics on http://127.0.0.1:{}/metrics", port);
+    let number_re = Regex::new(r"(-?\d+(?:\.\d+)?)\s*(%|°C)?")?;
+    loop {
+        let mut stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                tracing::warn!("Failed to accept a metrics connection: {}", e);
+                tokio::time::sleep(ACCEPT_RETRY).await;
+                continue;
+            }
+        };
+        let (results, stats, number_re) = (results.clone(), stats.clone(), number_re.clone());
+        tokio::spawn(async move {
+            match tokio::time::timeout(CONNECTION_TIMEOUT, answer(&mut stream, &results, &stats, &number_re)).await {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => tracing::debug!("Failed to answer metrics request: {}", e),
+                Err(_) => tracing::debug!("Metrics client timed out after {:?}", CONNECTION_TIMEOUT),
+            }
+        });
+    }
+}
+
+async fn answer(stream: &mut TcpStream, results: &Mutex<BarState>, stats: &Stats, number_re: &Regex) -> std::io::Result<()> {
+    let mut buf = [0u8; 1024];
+    let n = stream.read(&mut buf).await?;
+    let request = String::from_utf8_lossy(&buf[..n]);
+    let response = if request.starts_with("GET /metrics ") {
+        let body = render(&results.lock().unwrap().modules, stats, number_re);
+        format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+    } else {
+        "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+    };
+    stream.write_all(response.as_bytes()).await
+}
+
+fn render(results: &HashMap<ModuleId, String>, stats: &Stats, number_re: &Regex) -> String {
+    let mut out = String::new();
+
+    let mut values: Vec<_> = results.iter().collect();
+    values.sort();
+    for (id, value) in values {
+        let Some(caps) = number_re.captures(value) else {
+            continue;
+        };
+        let suffix = match caps.get(2).map(|m| m.as_str()) {
+            Some("%") => "percent",
+            Some("°C") => "celsius",
+            _ => "value",
+        };
+        let name = format!("dwm_bar_{}_{}", metric_name(id), suffix);
+        let _ = writeln!(out, "# TYPE {} gauge\n{} {}", name, name, &caps[1]);
+    }
+
+    let _ = writeln!(out, "# TYPE dwm_bar_module_duration_seconds histogram");
+    for (id, s) in stats.snapshot() {
+        let mut cumulative = 0;
+        for (le, count) in DURATION_BUCKETS.iter().zip(s.buckets) {
+            cumulative += count;
+            let _ = writeln!(out, "dwm_bar_module_duration_seconds_bucket{{module=\"{}\",le=\"{}\"}} {}", id, le, cumulative);
+        }
+        let _ = writeln!(out, "dwm_bar_module_duration_seconds_bucket{{module=\"{}\",le=\"+Inf\"}} {}", id, s.runs);
+        let _ = writeln!(out, "dwm_bar_module_duration_seconds_sum{{module=\"{}\"}} {}", id, s.total.as_secs_f64());
+        let _ = writeln!(out, "dwm_bar_module_duration_seconds_count{{module=\"{}\"}} {}", id, s.runs);
+    }
+    let _ = writeln!(out, "# TYPE dwm_bar_module_errors_total counter");
+    for (id, s) in stats.snapshot() {
+        let _ = writeln!(out, "dwm_bar_module_errors_total{{module=\"{}\"}} {}", id, s.errors);
+    }
+    out
+}
+
+fn metric_name(id: &str) -> String {
+    id.chars().map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' }).collect()
+}
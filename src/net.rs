@@ -0,0 +1,196 @@
+use crate::config::{IpVersion, NetConfig};
+use crate::registry::ModuleId;
+use anyhow::{Context, Result};
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+const SYS_NET: &str = "/sys/class/net";
+/// Answers with the caller's external address as plain text.
+const WAN_IP_URL: &str = "https://api.ipify.org";
+const WAN_IP_INTERVAL: Duration = Duration::from_secs(300);
+
+/// The latest external address, `None` while it can't be looked up.
+pub type WanIp = Arc<Mutex<Option<IpAddr>>>;
+
+/// Kinds of connection, most preferred first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Kind {
+    Ethernet,
+    Wifi,
+    /// USB tethering, e.g. a phone sharing its connection.
+    Usb,
+    Mobile,
+}
+
+/// Classifies an interface from sysfs. Virtual interfaces (bridges, tunnels, containers) have no
+/// `device` and are skipped.
+fn kind(iface: &Path) -> Option<Kind> {
+    let device = iface.join("device");
+    if !device.exists() {
+        return None;
+    }
+    let uevent = fs::read_to_string(device.join("uevent")).unwrap_or_default();
+    let link_type = fs::read_to_string(iface.join("type")).unwrap_or_default();
+    // Wi-Fi reports type 1 like Ethernet unless in monitor mode (801), so check for the wireless
+    // directory too.
+    if iface.join("wireless").exists() || iface.join("phy80211").exists() || link_type.trim() == "801" {
+        Some(Kind::Wifi)
+    } else if uevent.contains("DEVTYPE=wwan") || link_type.trim() == "519" {
+        Some(Kind::Mobile)
+    } else if fs::canonicalize(device.join("subsystem")).is_ok_and(|s| s.ends_with("usb")) {
+        Some(Kind::Usb)
+    } else {
+        Some(Kind::Ethernet)
+    }
+}
+
+/// The connection to show: the most preferred kind among interfaces that are up.
+fn active_connection() -> Option<(Kind, String)> {
+    fs::read_dir(SYS_NET)
+        .ok()?
+        .flatten()
+        .filter(|entry| fs::read_to_string(entry.path().join("operstate")).is_ok_and(|s| s.trim() == "up"))
+        .filter_map(|entry| Some((kind(&entry.path())?, entry.file_name().to_string_lossy().into_owned())))
+        .min()
+}
+
+/// Whether connection detection can work here.
+pub fn available() -> bool {
+    Path::new(SYS_NET).exists()
+}
+
+/// The active connection, e.g. `eth: 1Gbps`, `wifi: HomeNet 85%`, `usb: usb0` or `wwan: wwan0`;
+/// `net: down` when nothing is connected. Ethernet is preferred over Wi-Fi over tethering and
+/// mobile broadband.
+pub async fn connection_status(config: &NetConfig) -> Result<String> {
+    let Some((kind, iface)) = active_connection() else {
+        return Ok("net: down".to_string());
+    };
+    tracing::debug!(target: "monitor::net", ?kind, %iface, "Active connection");
+    Ok(match kind {
+        Kind::Ethernet => {
+            // In Mb/s; -1 when the driver doesn't know.
+            let speed = fs::read_to_string(Path::new(SYS_NET).join(&iface).join("speed")).ok().and_then(|s| s.trim().parse::<i64>().ok());
+            match speed {
+                Some(mbps) if mbps >= 1000 => format!("eth: {}Gbps", mbps as f64 / 1000.0),
+                Some(mbps) if mbps > 0 => format!("eth: {}Mbps", mbps),
+                _ => format!("eth: {}", iface),
+            }
+        }
+        Kind::Wifi => {
+            let link = iw_link(&iface).await.unwrap_or_default();
+            let ssid = link.lines().find_map(|line| line.trim().strip_prefix("SSID: ")).map(str::to_string);
+            let signal = wifi_signal(&iface).map(|pct| format!("{}%", pct));
+            let band = config.show_band.then(|| wifi_band(&link)).flatten().map(|band| format!("({})", band));
+            let mut parts: Vec<String> = ssid.into_iter().chain(signal).collect();
+            if parts.is_empty() {
+                parts.push(iface);
+            }
+            format!("wifi: {}", parts.into_iter().chain(band).collect::<Vec<_>>().join(" "))
+        }
+        Kind::Usb => format!("usb: {}", iface),
+        Kind::Mobile => format!("wwan: {}", iface),
+    })
+}
+
+/// The output of `iw dev <iface> link`, with the network name and frequency, if iw is installed.
+async fn iw_link(iface: &str) -> Option<String> {
+    if !crate::command_exists("iw") {
+        return None;
+    }
+    crate::run_command("iw", &["dev", iface, "link"]).await.ok()
+}
+
+/// The band of the `freq: 5180` (MHz, `5180.0` in newer versions) line of `iw dev <iface> link`.
+fn wifi_band(link: &str) -> Option<&'static str> {
+    let mhz: f64 = link.lines().find_map(|line| line.trim().strip_prefix("freq: "))?.trim().parse().ok()?;
+    // The 6 GHz band starts at 5925 MHz, above the last 5 GHz channel.
+    Some(match mhz as u32 {
+        0..3000 => "2.4G",
+        3000..5925 => "5G",
+        _ => "6G",
+    })
+}
+
+/// Link quality in percent from `/proc/net/wireless`, e.g. `wlan0: 0000   60.  -50.  -256 ...`
+/// where 60 is out of 70.
+fn wifi_signal(iface: &str) -> Option<u32> {
+    let table = fs::read_to_string("/proc/net/wireless").ok()?;
+    let line = table.lines().find(|line| line.trim_start().starts_with(&format!("{}:", iface)))?;
+    let quality: f64 = line.split_whitespace().nth(2)?.trim_end_matches('.').parse().ok()?;
+    Some(((quality * 100.0 / 70.0).round() as u32).min(100))
+}
+
+/// Looks up the external address every five minutes, separately from the net module's own
+/// polling, and triggers the module `id` when it changes.
+pub async fn poll_wan_ip(client: reqwest::Client, wan_ip: WanIp, id: ModuleId, trigger_tx: broadcast::Sender<ModuleId>) {
+    let mut interval = tokio::time::interval(WAN_IP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let ip = match fetch_wan_ip(&client).await {
+            Ok(ip) => Some(ip),
+            Err(e) => {
+                tracing::debug!(target: "monitor::net", "Cannot look up the WAN address: {:#}", e);
+                None
+            }
+        };
+        let previous = std::mem::replace(&mut *wan_ip.lock().unwrap(), ip);
+        if previous != ip {
+            tracing::debug!(target: "monitor::net", ?ip, "WAN address changed");
+            let _ = trigger_tx.send(id.clone());
+        }
+    }
+}
+
+async fn fetch_wan_ip(client: &reqwest::Client) -> Result<IpAddr> {
+    let body = client.get(WAN_IP_URL).send().await?.error_for_status()?.text().await?;
+    body.trim().parse().with_context(|| format!("Not an address from {}: {}", WAN_IP_URL, body.trim()))
+}
+
+/// The interface carrying the default route, from `ip route show default`.
+async fn default_interface() -> Result<String> {
+    let output = crate::run_command("ip", &["-o", "route", "show", "default"]).await?;
+    let mut fields = output.split_whitespace();
+    fields.find(|&f| f == "dev");
+    fields.next().map(str::to_string).context("No default route")
+}
+
+/// The addresses of `iface`, in the order `ip addr` lists them.
+async fn addresses(iface: &str) -> Result<Vec<IpAddr>> {
+    let output = crate::run_command("ip", &["-o", "addr", "show", "dev", iface]).await?;
+    // `2: wlan0    inet 192.168.1.5/24 brd 192.168.1.255 scope global ...`
+    Ok(output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            fields.find(|&f| f == "inet" || f == "inet6");
+            fields.next()?.split('/').next()?.parse().ok()
+        })
+        .collect())
+}
+
+fn is_link_local(addr: &IpAddr) -> bool {
+    matches!(addr, IpAddr::V6(v6) if v6.segments()[0] & 0xffc0 == 0xfe80)
+}
+
+/// The addresses to show for `config.ip_version`, space separated. IPv6 link-local addresses are
+/// only shown in `v6` mode when there is no other IPv6 address.
+pub async fn ip_suffix(config: &NetConfig) -> Result<String> {
+    let iface = match &config.interface {
+        Some(iface) => iface.clone(),
+        None => default_interface().await?,
+    };
+    let addrs = addresses(&iface).await?;
+    let v4 = addrs.iter().find(|a| a.is_ipv4());
+    let v6_global = addrs.iter().find(|a| a.is_ipv6() && !is_link_local(a));
+    let shown: Vec<&IpAddr> = match config.ip_version {
+        IpVersion::V4 => v4.into_iter().collect(),
+        IpVersion::V6 => v6_global.or_else(|| addrs.iter().find(|a| a.is_ipv6())).into_iter().collect(),
+        IpVersion::Both => v4.into_iter().chain(v6_global).collect(),
+    };
+    Ok(shown.iter().map(|a| a.to_string()).collect::<Vec<_>>().join(" "))
+}
@@ -0,0 +1,57 @@
+use crate::registry::{ModuleId, ModuleRegistry};
+use futures_util::StreamExt;
+use rtnetlink::MulticastGroup;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Modules whose output depends on interface state.
+const NETWORK_MODULES: &[&str] = &["net", "vpn", "public_ip"];
+/// Link changes come in bursts (carrier, then addresses, then routes); wait this long after the
+/// first event so the burst triggers a single refresh.
+const SETTLE: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Refreshes the network modules as soon as an interface goes up or down or gains or loses an
+/// address, instead of waiting for their next poll. Polling carries on regardless, so if the
+/// netlink socket can't be opened this just gives up quietly.
+pub async fn watch(trigger_tx: broadcast::Sender<ModuleId>, registry: Arc<ModuleRegistry>) {
+    let modules: Vec<ModuleId> = NETWORK_MODULES.iter().filter_map(|id| registry.get(id)).collect();
+    if modules.is_empty() {
+        return;
+    }
+    let (mut backoff, mut connected) = (Duration::from_secs(1), false);
+    loop {
+        match listen(&trigger_tx, &modules).await {
+            Ok(()) => {
+                connected = true;
+                tracing::warn!("Netlink event stream ended, reconnecting in {:?}", backoff);
+            }
+            Err(e) if !connected => {
+                tracing::debug!("Netlink listener unavailable ({}), relying on polling", e);
+                return;
+            }
+            Err(e) => tracing::warn!("Netlink reconnect failed ({}), retrying in {:?}", e, backoff),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Runs until the event stream ends; errors only if the socket can't be set up at all.
+async fn listen(trigger_tx: &broadcast::Sender<ModuleId>, modules: &[ModuleId]) -> std::io::Result<()> {
+    let groups = [MulticastGroup::Link, MulticastGroup::Ipv4Ifaddr, MulticastGroup::Ipv6Ifaddr];
+    let (connection, _handle, mut messages) = rtnetlink::new_multicast_connection(&groups)?;
+    let connection = tokio::spawn(connection);
+    tracing::debug!("Listening for netlink link and address changes");
+    while messages.next().await.is_some() {
+        // Swallow the rest of the burst before refreshing.
+        let _ = tokio::time::timeout(SETTLE, async { while messages.next().await.is_some() {} }).await;
+        tracing::debug!("Network interface state changed, refreshing network modules");
+        for id in modules {
+            let _ = trigger_tx.send(id.clone());
+        }
+    }
+    connection.abort();
+    Ok(())
+}
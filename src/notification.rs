@@ -0,0 +1,187 @@
+use crate::config::NotificationConfig;
+use crate::util::truncate;
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Trigger file name that marks the notification history as read.
+pub const CLEAR_TRIGGER: &str = "notification_clear";
+/// Application names are cut to this many characters.
+const APP_NAME_WIDTH: usize = 12;
+
+static CLEAR_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Asks the notification module to move its watermark to the newest notification on its next run.
+pub fn request_clear() {
+    CLEAR_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// `dunstctl history` output: D-Bus style `{"type": ..., "data": ...}` wrappers around a list of
+/// notifications.
+#[derive(Deserialize)]
+struct History {
+    data: Vec<Vec<Entry>>,
+}
+
+#[derive(Deserialize)]
+struct Entry {
+    id: Field<i64>,
+    summary: Field<String>,
+    appname: Option<Field<String>>,
+}
+
+#[derive(Deserialize)]
+struct Field<T> {
+    data: T,
+}
+
+/// The notification daemon whose do-not-disturb state is shown.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotificationDaemon {
+    Dunst,
+    Mako,
+    SwayNc,
+}
+
+impl NotificationDaemon {
+    /// Picks the first daemon whose control tool is installed.
+    pub fn detect() -> Option<Self> {
+        [
+            ("dunstctl", NotificationDaemon::Dunst),
+            ("makoctl", NotificationDaemon::Mako),
+            ("swaync-client", NotificationDaemon::SwayNc),
+        ]
+        .into_iter()
+        .find(|(cmd, _)| crate::command_exists(cmd))
+        .map(|(_, daemon)| daemon)
+    }
+
+    /// Whether notifications are paused (dunst) or in do-not-disturb mode (mako, SwayNC).
+    pub async fn query_paused(self) -> Result<bool> {
+        let paused = match self {
+            NotificationDaemon::Dunst => crate::run_command("dunstctl", &["is-paused"]).await?.trim() == "true",
+            // `makoctl mode` lists the active modes, one per line.
+            NotificationDaemon::Mako => crate::run_command("makoctl", &["mode"]).await?.lines().any(|mode| mode.trim() == "do-not-disturb"),
+            NotificationDaemon::SwayNc => crate::run_command("swaync-client", &["--get-dnd"]).await?.trim() == "true",
+        };
+        tracing::debug!(target: "monitor::notification", daemon = ?self, paused, "Queried notification daemon");
+        Ok(paused)
+    }
+}
+
+/// Notification history state: the ID of the newest notification at the last clear, persisted
+/// so unread counts survive a bar restart.
+pub struct Notifications {
+    daemon: NotificationDaemon,
+    config: NotificationConfig,
+    watermark: Mutex<i64>,
+}
+
+impl Notifications {
+    pub fn new(daemon: NotificationDaemon, config: NotificationConfig) -> Self {
+        let watermark = watermark_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|s| s.trim().parse().ok())
+            .unwrap_or(0);
+        Notifications { daemon, config, watermark: Mutex::new(watermark) }
+    }
+
+    fn set_watermark(&self, id: i64) {
+        *self.watermark.lock().unwrap() = id;
+        if let Some(path) = watermark_path() {
+            let result = path.parent().map_or(Ok(()), std::fs::create_dir_all).and_then(|_| std::fs::write(&path, id.to_string()));
+            if let Err(e) = result {
+                tracing::warn!(target: "monitor::notification", "Cannot save notification watermark to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// Unread notifications, newest first.
+    async fn unread(&self) -> Result<Vec<Entry>> {
+        let output = crate::run_command("dunstctl", &["history"]).await?;
+        let history: History = serde_json::from_str(&output)?;
+        let mut entries: Vec<Entry> = history.data.into_iter().flatten().collect();
+        entries.sort_by_key(|e| std::cmp::Reverse(e.id.data));
+        let newest = entries.first().map_or(0, |e| e.id.data);
+
+        if CLEAR_REQUESTED.swap(false, Ordering::Relaxed) {
+            self.set_watermark(newest);
+        } else if newest < *self.watermark.lock().unwrap() {
+            // dunst restarted and began numbering from 1 again.
+            self.set_watermark(0);
+        }
+        let watermark = *self.watermark.lock().unwrap();
+        entries.retain(|e| e.id.data > watermark);
+        Ok(entries)
+    }
+}
+
+fn watermark_path() -> Option<PathBuf> {
+    let state = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?;
+    Some(state.join("dwm-status-bar-rs/notification-watermark"))
+}
+
+/// Makes notification text safe for the bar: drops markup tags, status2d `^` escapes and line
+/// breaks, then caps the width.
+pub fn sanitize(text: &str, max_chars: usize) -> String {
+    let mut plain = String::with_capacity(text.len());
+    let mut in_tag = false;
+    for c in text.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if in_tag => {}
+            '^' => {}
+            c if c.is_whitespace() || c.is_control() => plain.push(' '),
+            c => plain.push(c),
+        }
+    }
+    let plain = plain.replace("&lt;", "<").replace("&gt;", ">").replace("&quot;", "\"").replace("&apos;", "'").replace("&amp;", "&");
+    truncate(&plain.split_whitespace().collect::<Vec<_>>().join(" "), max_chars)
+}
+
+/// The applications with the most notifications in `entries`, e.g. `Discord(7) Email(3)`.
+fn top_apps(entries: &[Entry], max_apps: u8) -> String {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for entry in entries {
+        let name = entry.appname.as_ref().map_or("", |name| name.data.as_str());
+        *counts.entry(if name.is_empty() { "unknown" } else { name }).or_default() += 1;
+    }
+    let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    counts
+        .into_iter()
+        .take(max_apps.into())
+        .map(|(name, count)| format!("{}({})", sanitize(name, APP_NAME_WIDTH), count))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// `n: disabled` while paused, otherwise the unread count with `history`, followed by the unread
+/// count per application with `show_apps`, e.g. `n: disabled | Email(3) Discord(7)`.
+pub async fn monitor(notifications: &Notifications) -> Result<String> {
+    let config = &notifications.config;
+    let paused = notifications.daemon.query_paused().await?;
+    // Only dunst exposes its history.
+    let dunst = notifications.daemon == NotificationDaemon::Dunst;
+    let unread = if dunst && (config.show_apps || (config.history && !paused)) { notifications.unread().await? } else { Vec::new() };
+    let status = match unread.first() {
+        _ if paused => "n: disabled".to_string(),
+        Some(newest) if config.history && config.summary => {
+            format!("n: {} ‹{}›", unread.len(), sanitize(&newest.summary.data, config.summary_width))
+        }
+        Some(_) if config.history => format!("n: {}", unread.len()),
+        _ => String::new(),
+    };
+    let apps = if config.show_apps { top_apps(&unread, config.max_apps) } else { String::new() };
+    Ok(match (status.is_empty(), apps.is_empty()) {
+        (_, true) => status,
+        (true, false) => format!("n: {}", apps),
+        (false, false) => format!("{} | {}", status, apps),
+    })
+}
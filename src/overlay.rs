@@ -0,0 +1,107 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tokio::time::Instant;
+
+/// Trigger file whose contents, `[seconds] message`, flash a message at the front of the bar.
+pub const TRIGGER: &str = "overlay";
+const DEFAULT_DURATION: Duration = Duration::from_secs(3);
+/// Longer durations are cut to this, so a huge one can't overflow the deadline.
+const MAX_DURATION: Duration = Duration::from_secs(3600);
+const MAX_CHARS: usize = 80;
+
+/// A short-lived message shown before every module, e.g. `screenshot saved`. A new message
+/// replaces the current one along with its timeout.
+#[derive(Clone, Default)]
+pub struct Overlay {
+    current: Arc<Mutex<Option<(String, Instant)>>>,
+    changed: Arc<Notify>,
+}
+
+impl Overlay {
+    /// Shows `line` as parsed by [`parse`]. An empty message clears the overlay.
+    pub fn show(&self, line: &str) {
+        let (duration, text) = parse(line);
+        tracing::debug!("Overlay '{}' for {:?}", text, duration);
+        *self.current.lock().unwrap() = (!text.is_empty()).then(|| (text, Instant::now() + duration));
+        self.changed.notify_one();
+    }
+
+    /// The message, unless it has expired.
+    pub fn text(&self) -> Option<String> {
+        let current = self.current.lock().unwrap();
+        current.as_ref().filter(|(_, until)| Instant::now() < *until).map(|(text, _)| text.clone())
+    }
+
+    /// When the current message expires.
+    pub fn deadline(&self) -> Option<Instant> {
+        self.current.lock().unwrap().as_ref().map(|(_, until)| *until)
+    }
+
+    /// Forgets the message once it has expired.
+    pub fn clear_expired(&self) {
+        let mut current = self.current.lock().unwrap();
+        if current.as_ref().is_some_and(|(_, until)| Instant::now() >= *until) {
+            *current = None;
+        }
+    }
+
+    /// Resolves once a message has been shown since the last call.
+    pub async fn changed(&self) {
+        self.changed.notified().await;
+    }
+}
+
+/// Splits an optional leading duration in seconds (default 3, at most an hour) from the message,
+/// e.g. `5 build failed`. Line breaks and other control characters become spaces, `^` (status2d
+/// escapes) is dropped, and the message is capped at 80 characters.
+pub fn parse(line: &str) -> (Duration, String) {
+    let line = line.trim();
+    let (duration, message) = match line.split_once(char::is_whitespace) {
+        Some((secs, rest)) => match secs.parse::<f64>().ok().filter(|s| (0.0..=f64::MAX).contains(s)) {
+            Some(secs) => (Duration::from_secs_f64(secs.min(MAX_DURATION.as_secs_f64())), rest),
+            None => (DEFAULT_DURATION, line),
+        },
+        None => (DEFAULT_DURATION, line),
+    };
+    let cleaned: String = message.chars().filter(|&c| c != '^').map(|c| if c.is_control() { ' ' } else { c }).collect();
+    let collapsed = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    (duration, crate::util::truncate(&collapsed, MAX_CHARS))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leading_duration() {
+        assert_eq!(parse("5 build failed"), (Duration::from_secs(5), "build failed".to_string()));
+        assert_eq!(parse("0.5 saved"), (Duration::from_millis(500), "saved".to_string()));
+    }
+
+    #[test]
+    fn missing_duration_uses_default() {
+        assert_eq!(parse("screenshot saved"), (DEFAULT_DURATION, "screenshot saved".to_string()));
+        assert_eq!(parse("saved"), (DEFAULT_DURATION, "saved".to_string()));
+    }
+
+    #[test]
+    fn huge_duration_is_capped() {
+        assert_eq!(parse("1e30 msg"), (MAX_DURATION, "msg".to_string()));
+        assert_eq!(parse("1e18 msg"), (MAX_DURATION, "msg".to_string()));
+        // The overlay can be shown without the deadline overflowing.
+        Overlay::default().show("1e18 msg");
+    }
+
+    #[test]
+    fn negative_or_non_finite_duration_is_part_of_the_message() {
+        assert_eq!(parse("-5 msg"), (DEFAULT_DURATION, "-5 msg".to_string()));
+        assert_eq!(parse("inf msg"), (DEFAULT_DURATION, "inf msg".to_string()));
+        assert_eq!(parse("NaN msg"), (DEFAULT_DURATION, "NaN msg".to_string()));
+    }
+
+    #[test]
+    fn control_characters_and_escapes_are_cleaned() {
+        assert_eq!(parse("2 ^c#ff0000^line\none").1, "c#ff0000line one");
+    }
+}
@@ -0,0 +1,59 @@
+use crate::config::{BarProtocol, Config};
+use crate::registry::{ModuleId, ModuleRegistry};
+use std::collections::HashMap;
+
+/// Per-module fonts for `bar_protocol = "pango"`. Colors come from the shared resolved colors,
+/// so a SIGHUP reload reaches Pango output too.
+#[derive(Debug, Clone)]
+pub struct Pango {
+    fonts: HashMap<ModuleId, String>,
+}
+
+impl Pango {
+    /// `None` unless Pango output is configured.
+    pub fn new(config: &Config, registry: &ModuleRegistry) -> Option<Self> {
+        if config.bar_protocol != BarProtocol::Pango {
+            return None;
+        }
+        let fonts = registry
+            .order()
+            .iter()
+            .filter_map(|id| Some((id.clone(), config.modules.get(&**id)?.font.clone()?)))
+            .collect();
+        Some(Pango { fonts })
+    }
+
+    /// Escapes `text` and wraps it in a span carrying the module's color and font, if it has any.
+    pub fn markup(&self, id: &ModuleId, text: &str, color: Option<&str>) -> String {
+        let text = escape(text);
+        let mut attrs = String::new();
+        if let Some(color) = color {
+            attrs.push_str(&format!(" color='{}'", escape(color)));
+        }
+        if let Some(font) = self.fonts.get(id) {
+            attrs.push_str(&format!(" font='{}'", escape(font)));
+        }
+        if attrs.is_empty() {
+            text
+        } else {
+            format!("<span{}>{}</span>", attrs, text)
+        }
+    }
+}
+
+/// Escapes the characters that are special in Pango markup, so module output such as a track
+/// title containing `<` or `&` can't break the whole bar.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '\'' => escaped.push_str("&apos;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,83 @@
+use crate::config::Config;
+use crate::registry::{ModuleId, ModuleRegistry};
+use anyhow::Result;
+use std::fs;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::sync::{broadcast, mpsc};
+
+/// Battery level is re-checked this often even without ACPI events, since the charge dropping
+/// below the threshold doesn't generate one.
+const RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Whether the system runs on battery below `threshold` percent, from the first battery in sysfs.
+fn low_battery(threshold: u8) -> bool {
+    let Ok(entries) = fs::read_dir("/sys/class/power_supply") else {
+        return false;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if fs::read_to_string(path.join("type")).is_ok_and(|t| t.trim() == "Battery") {
+            let discharging = fs::read_to_string(path.join("status")).is_ok_and(|s| s.trim() == "Discharging");
+            let capacity = fs::read_to_string(path.join("capacity")).ok().and_then(|c| c.trim().parse::<u8>().ok());
+            return discharging && capacity.is_some_and(|c| c < threshold);
+        }
+    }
+    false
+}
+
+/// Keeps `low_power` in sync with the battery, re-checking on every `acpi_listen` event (AC
+/// plugged or unplugged) and once a minute. On a change, modules marked `low_power_disable` are
+/// triggered so they hide or come back right away.
+pub async fn watch(low_power: Arc<AtomicBool>, config: Arc<Config>, registry: Arc<ModuleRegistry>, trigger_tx: broadcast::Sender<ModuleId>) {
+    let threshold = config.low_power_mode_battery_pct;
+    if threshold == 0 {
+        return;
+    }
+    let affected: Vec<ModuleId> = registry.order().iter().filter(|id| config.module(id).low_power_disable).cloned().collect();
+
+    let (event_tx, mut event_rx) = mpsc::unbounded_channel();
+    if crate::command_exists("acpi_listen") {
+        tokio::spawn(async move {
+            if let Err(e) = listen_acpi(event_tx).await {
+                tracing::warn!("acpi_listen stopped, checking the battery once a minute only: {}", e);
+            }
+        });
+    }
+
+    let mut interval = tokio::time::interval(RECHECK_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {},
+            Some(()) = event_rx.recv() => {},
+        }
+        let low = low_battery(threshold);
+        if low_power.swap(low, Ordering::Relaxed) != low {
+            if low {
+                tracing::info!("Battery below {}%, entering low-power mode", threshold);
+            } else {
+                tracing::info!("Leaving low-power mode");
+            }
+            for id in &affected {
+                let _ = trigger_tx.send(id.clone());
+            }
+        }
+    }
+}
+
+async fn listen_acpi(event_tx: mpsc::UnboundedSender<()>) -> Result<()> {
+    let mut child = tokio::process::Command::new("acpi_listen").stdout(std::process::Stdio::piped()).kill_on_drop(true).spawn()?;
+    let stdout = child.stdout.take().ok_or_else(|| anyhow::anyhow!("no stdout"))?;
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.starts_with("ac_adapter") || line.starts_with("battery") {
+            tracing::debug!("ACPI event: {}", line);
+            if event_tx.send(()).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
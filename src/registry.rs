@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// A module ID. Cheap to clone, so it can travel over the update and trigger channels.
+pub type ModuleId = Arc<str>;
+
+/// In the display order, modules before this are pinned to the left end of the bar.
+pub const LEFT_END: &str = "__left_end__";
+/// In the display order, modules after this are pinned to the right end of the bar.
+pub const RIGHT_END: &str = "__right_end__";
+
+/// Where on the bar a module is rendered, like polybar's `modules-left/center/right`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Section {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+/// The set of module IDs known at runtime, in display order.
+#[derive(Debug, Clone, Default)]
+pub struct ModuleRegistry {
+    order: Vec<ModuleId>,
+    pinned: HashMap<ModuleId, Section>,
+}
+
+impl ModuleRegistry {
+    /// Builds a registry from IDs in display order. Duplicates keep their first position. The
+    /// `LEFT_END` and `RIGHT_END` sentinels pin the modules before or after them to that end.
+    pub fn new<I, S>(ids: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut order: Vec<ModuleId> = Vec::new();
+        let (mut left_end, mut right_start) = (None, None);
+        for id in ids {
+            match id.as_ref() {
+                LEFT_END => left_end = Some(order.len()),
+                RIGHT_END => right_start = Some(order.len()),
+                id if !order.iter().any(|m| m.as_ref() == id) => order.push(id.into()),
+                _ => {}
+            }
+        }
+        let pinned = order
+            .iter()
+            .enumerate()
+            .filter_map(|(position, id)| {
+                let section = if left_end.is_some_and(|end| position < end) {
+                    Section::Left
+                } else if right_start.is_some_and(|start| position >= start) {
+                    Section::Right
+                } else {
+                    return None;
+                };
+                Some((id.clone(), section))
+            })
+            .collect();
+        ModuleRegistry { order, pinned }
+    }
+
+    /// The section `id` is pinned to; unpinned modules are in the center.
+    pub fn section(&self, id: &str) -> Section {
+        self.pinned.get(id).copied().unwrap_or_default()
+    }
+
+    pub fn order(&self) -> &[ModuleId] {
+        &self.order
+    }
+
+    /// Returns the shared ID for `id`, if it is registered.
+    pub fn get(&self, id: &str) -> Option<ModuleId> {
+        self.order.iter().find(|m| m.as_ref() == id).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(registry: &ModuleRegistry) -> Vec<&str> {
+        registry.order().iter().map(|id| id.as_ref()).collect()
+    }
+
+    #[test]
+    fn keeps_the_configured_order_without_duplicates() {
+        let registry = ModuleRegistry::new(["datetime", "cpu_load", "datetime", "ram"]);
+        assert_eq!(ids(&registry), ["datetime", "cpu_load", "ram"]);
+        assert!(registry.order().iter().all(|id| registry.section(id) == Section::Center));
+    }
+
+    #[test]
+    fn sentinels_pin_left_and_right_sections() {
+        let registry = ModuleRegistry::new(["workspace", LEFT_END, "cpu_load", "ram", RIGHT_END, "battery", "datetime"]);
+        assert_eq!(ids(&registry), ["workspace", "cpu_load", "ram", "battery", "datetime"]);
+        assert_eq!(registry.section("workspace"), Section::Left);
+        assert_eq!(registry.section("cpu_load"), Section::Center);
+        assert_eq!(registry.section("ram"), Section::Center);
+        assert_eq!(registry.section("battery"), Section::Right);
+        assert_eq!(registry.section("datetime"), Section::Right);
+    }
+
+    #[test]
+    fn unknown_ids_keep_their_place() {
+        // No monitor is started for an unknown ID, but the order is taken as configured.
+        let registry = ModuleRegistry::new(["cpu_load", "no_such_module", "datetime"]);
+        assert_eq!(ids(&registry), ["cpu_load", "no_such_module", "datetime"]);
+        assert_eq!(registry.get("no_such_module").as_deref(), Some("no_such_module"));
+        assert_eq!(registry.get("battery"), None);
+        assert_eq!(registry.section("battery"), Section::Center);
+    }
+
+    #[test]
+    fn shared_ids_are_handed_out() {
+        let registry = ModuleRegistry::new(["disk_root", "disk_home"]);
+        let id = registry.get("disk_home").unwrap();
+        assert!(Arc::ptr_eq(&id, &registry.order()[1]));
+    }
+
+    #[test]
+    fn triggers_route_by_dynamic_id() {
+        // Two instances of the disk monitor, each listening for its own ID on the shared channel.
+        let registry = ModuleRegistry::new(["disk_root", "disk_home", "datetime"]);
+        let (tx, _) = tokio::sync::broadcast::channel::<ModuleId>(8);
+        let mut listeners: Vec<_> = ["disk_root", "disk_home"].iter().map(|&id| (registry.get(id).unwrap(), tx.subscribe())).collect();
+
+        let name = String::from("disk_") + "home";
+        tx.send(registry.get(&name).unwrap()).unwrap();
+        let mut triggered = Vec::new();
+        for (id, rx) in &mut listeners {
+            if rx.try_recv().is_ok_and(|sent| sent == *id) {
+                triggered.push(id.to_string());
+            }
+        }
+        assert_eq!(triggered, ["disk_home"]);
+        // Names that aren't registered don't route anywhere.
+        assert!(registry.get("disk_var").is_none());
+    }
+}
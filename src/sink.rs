@@ -0,0 +1,207 @@
+use crate::bar::{Bar, Markup, Separators};
+use crate::budget::Budget;
+use crate::config::SinkConfig;
+use crate::pango::Pango;
+use crate::registry::{ModuleId, ModuleRegistry, Section};
+#[cfg(feature = "x11")]
+use crate::xroot::XRoot;
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Write errors for a sink are logged at most this often, so a full disk doesn't flood the log.
+const ERROR_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+enum Output {
+    #[cfg(feature = "x11")]
+    XRoot(Box<XRoot>),
+    File(PathBuf),
+    Stdout,
+    #[cfg(feature = "wayland")]
+    Swaybar,
+}
+
+/// One destination for the bar, with its own module subset and order.
+pub struct Sink {
+    output: Output,
+    /// Sorted by section, so pinned modules end up at the ends whatever the sink's own order.
+    order: Vec<(ModuleId, Section)>,
+    budget: Option<Budget>,
+    pango: Option<Pango>,
+    separators: Separators,
+    last: Option<String>,
+    last_error_log: Option<Instant>,
+    suppressed_errors: u32,
+}
+
+impl Sink {
+    pub fn new(
+        config: &SinkConfig,
+        registry: &ModuleRegistry,
+        budget: Option<Budget>,
+        pango: Option<Pango>,
+        separators: Separators,
+    ) -> Result<Self> {
+        let output = match config {
+            #[cfg(feature = "x11")]
+            SinkConfig::Xroot { .. } => Output::XRoot(Box::new(XRoot::connect()?)),
+            #[cfg(not(feature = "x11"))]
+            SinkConfig::Xroot { .. } => anyhow::bail!("The xroot sink needs a build with the `x11` feature"),
+            SinkConfig::File { path, .. } => Output::File(path.clone()),
+            SinkConfig::Stdout { .. } => Output::Stdout,
+            #[cfg(feature = "wayland")]
+            SinkConfig::Swaybar { .. } => {
+                // i3bar protocol header, followed by an endless JSON array of status lines.
+                println!("{{\"version\":1}}\n[");
+                Output::Swaybar
+            }
+            #[cfg(not(feature = "wayland"))]
+            SinkConfig::Swaybar { .. } => anyhow::bail!("The swaybar sink needs a build with the `wayland` feature"),
+        };
+        let ids: Vec<ModuleId> = match config.modules() {
+            Some(ids) => ids
+                .iter()
+                .filter_map(|id| {
+                    let found = registry.get(id);
+                    if found.is_none() {
+                        tracing::warn!("Sink lists unknown module '{}', ignoring it", id);
+                    }
+                    found
+                })
+                .collect(),
+            None => registry.order().to_vec(),
+        };
+        let mut order: Vec<(ModuleId, Section)> = ids.into_iter().map(|id| {
+            let section = registry.section(&id);
+            (id, section)
+        }).collect();
+        order.sort_by_key(|&(_, section)| section);
+        Ok(Sink { output, order, budget, pango, separators, last: None, last_error_log: None, suppressed_errors: 0 })
+    }
+
+    /// Assembles this sink's modules in the format its output expects.
+    /// Modules in `hidden` are left out; `overlay` goes before all of them.
+    pub fn render(
+        &self,
+        results: &HashMap<ModuleId, String>,
+        colors: &HashMap<ModuleId, String>,
+        hidden: &HashSet<ModuleId>,
+        overlay: Option<&str>,
+    ) -> String {
+        let order: Vec<(ModuleId, Section)> = self.order.iter().filter(|(id, _)| !hidden.contains(id)).cloned().collect();
+        #[cfg(feature = "wayland")]
+        if matches!(self.output, Output::Swaybar) {
+            let ids: Vec<ModuleId> = order.into_iter().map(|(id, _)| id).collect();
+            return swaybar_line(&ids, results, colors, self.pango.as_ref(), overlay);
+        }
+        let markup = match &self.pango {
+            Some(pango) => Markup::Pango(pango, colors),
+            None if self.status2d() => Markup::Status2d(colors),
+            None => Markup::Plain,
+        };
+        let bar = Bar { order: &order, results, markup, budget: self.budget.as_ref(), overlay, separators: &self.separators };
+        bar.assemble()
+    }
+
+    /// Whether this sink renders dwm status2d color escapes.
+    fn status2d(&self) -> bool {
+        #[cfg(feature = "x11")]
+        if matches!(self.output, Output::XRoot(_)) {
+            return true;
+        }
+        false
+    }
+
+    /// Writes `bar` unless it is identical to what this sink last received.
+    pub fn write(&mut self, bar: String) {
+        if self.last.as_ref() == Some(&bar) {
+            return;
+        }
+        let result = match &self.output {
+            #[cfg(feature = "x11")]
+            Output::XRoot(xroot) => xroot.set_name(&bar),
+            Output::File(path) => crate::util::write_atomic(path, &format!("{}\n", bar)),
+            Output::Stdout => write_line(&bar),
+            #[cfg(feature = "wayland")]
+            Output::Swaybar => write_line(&format!("{},", bar)),
+        };
+        match result {
+            Ok(()) => {
+                if self.last_error_log.take().is_some() {
+                    tracing::info!("Writing to {} works again", self.describe());
+                }
+                self.suppressed_errors = 0;
+                self.last = Some(bar);
+            }
+            Err(e) => {
+                if self.last_error_log.is_some_and(|t| t.elapsed() < ERROR_LOG_INTERVAL) {
+                    self.suppressed_errors += 1;
+                    return;
+                }
+                if self.suppressed_errors > 0 {
+                    tracing::error!("Failed to write bar to {}: {} ({} more failures since the last report)", self.describe(), e, self.suppressed_errors);
+                } else {
+                    tracing::error!("Failed to write bar to {}: {}", self.describe(), e);
+                }
+                self.last_error_log = Some(Instant::now());
+                self.suppressed_errors = 0;
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match &self.output {
+            #[cfg(feature = "x11")]
+            Output::XRoot(_) => "root window".to_string(),
+            Output::File(path) => path.display().to_string(),
+            Output::Stdout => "stdout".to_string(),
+            #[cfg(feature = "wayland")]
+            Output::Swaybar => "swaybar".to_string(),
+        }
+    }
+}
+
+/// One i3bar protocol status line: a block per module, carrying the module's color if it has one.
+/// With Pango, the text is escaped and wrapped in the module's font span.
+#[cfg(feature = "wayland")]
+fn swaybar_line(
+    order: &[ModuleId],
+    results: &HashMap<ModuleId, String>,
+    colors: &HashMap<ModuleId, String>,
+    pango: Option<&Pango>,
+    overlay: Option<&str>,
+) -> String {
+    let full_text = |id: &ModuleId, text: &str| match pango {
+        Some(pango) => pango.markup(id, text, None),
+        None => text.to_string(),
+    };
+    let overlay_id = ModuleId::from(crate::overlay::TRIGGER);
+    let overlay = overlay.map(|text| serde_json::json!({ "name": crate::overlay::TRIGGER, "full_text": full_text(&overlay_id, text), "urgent": true }));
+    let blocks: Vec<serde_json::Value> = overlay
+        .into_iter()
+        .chain(order.iter().filter_map(|id| {
+            let text = results.get(id).filter(|s| !s.is_empty())?;
+            let mut block = serde_json::json!({ "name": id.as_ref(), "full_text": full_text(id, text) });
+            if let Some(color) = colors.get(id) {
+                block["color"] = color.as_str().into();
+            }
+            Some(block)
+        }))
+        .map(|mut block| {
+            if pango.is_some() {
+                block["markup"] = "pango".into();
+            }
+            block
+        })
+        .collect();
+    serde_json::Value::Array(blocks).to_string()
+}
+
+fn write_line(line: &str) -> Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    writeln!(stdout, "{}", line)?;
+    stdout.flush()?;
+    Ok(())
+}
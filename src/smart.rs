@@ -0,0 +1,122 @@
+use crate::registry::ModuleId;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+/// Spinning disks may have to wake up before they answer.
+const SMART_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The overall SMART verdict for a disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    Passed,
+    /// The self-assessment failed, or an attribute is or was below its threshold.
+    Failed,
+}
+
+impl Health {
+    pub fn glyph(self) -> &'static str {
+        match self {
+            Health::Passed => "✓",
+            Health::Failed => "✗",
+        }
+    }
+}
+
+/// The latest verdict, kept by `poll` and read by the disk module. `None` until the first
+/// successful query, or while queries fail.
+pub type SmartHealth = Arc<Mutex<Option<Health>>>;
+
+/// The whole disk holding the filesystem mounted at `mount`, e.g. `/dev/nvme0n1` for `/` on
+/// `/dev/nvme0n1p2`. Device-mapper devices (LUKS, LVM) are followed to the disk below them.
+pub fn device_for(mount: &Path) -> Option<PathBuf> {
+    let mounts = fs::read_to_string("/proc/self/mounts").ok()?;
+    // The last entry wins, as later mounts hide earlier ones.
+    let source = mounts
+        .lines()
+        .rev()
+        .find_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (source, target) = (fields.next()?, fields.next()?);
+            (Path::new(target) == mount).then_some(source)
+        })?;
+    // Resolves `/dev/disk/by-uuid/...` and `/dev/mapper/...` links to the kernel name.
+    let device = fs::canonicalize(source).ok()?;
+    let disk = disk_of(device.file_name()?.to_str()?)?;
+    Some(Path::new("/dev").join(disk))
+}
+
+/// The disk behind block device `name`, from its sysfs directory.
+fn disk_of(name: &str) -> Option<String> {
+    let sys = fs::canonicalize(Path::new("/sys/class/block").join(name)).ok()?;
+    if let Some(slave) = fs::read_dir(sys.join("slaves")).ok().and_then(|mut slaves| slaves.next()?.ok()) {
+        return disk_of(slave.file_name().to_str()?);
+    }
+    // A partition's sysfs directory sits inside its disk's.
+    if sys.join("partition").exists() {
+        return Some(sys.parent()?.file_name()?.to_str()?.to_string());
+    }
+    Some(name.to_string())
+}
+
+/// Queries `smartctl -H -A -j`, through `sudo -n` with `sudo` since SMART needs root.
+async fn query(device: &Path, sudo: bool) -> Result<Health> {
+    let mut command = if sudo {
+        let mut command = tokio::process::Command::new("sudo");
+        command.args(["-n", "smartctl"]);
+        command
+    } else {
+        tokio::process::Command::new("smartctl")
+    };
+    // Not run_command: smartctl reports a failing disk through its exit status, and the JSON on
+    // stdout is wanted either way.
+    let child = command.args(["-H", "-A", "-j"]).arg(device).stdin(Stdio::null()).stderr(Stdio::null()).kill_on_drop(true).output();
+    let output = tokio::time::timeout(SMART_TIMEOUT, child).await.with_context(|| format!("smartctl timed out after {:?}", SMART_TIMEOUT))??;
+    let report: Value = serde_json::from_slice(&output.stdout).with_context(|| format!("No JSON from smartctl ({})", output.status))?;
+    parse(&report)
+}
+
+/// The verdict in `smartctl -H -A -j` output.
+fn parse(report: &Value) -> Result<Health> {
+    let Some(passed) = report["smart_status"]["passed"].as_bool() else {
+        let message = report["smartctl"]["messages"][0]["string"].as_str().unwrap_or("no SMART status in the output");
+        anyhow::bail!("smartctl: {}", message);
+    };
+    // ATA attributes note when they fell below their threshold, `now` or in the `past`.
+    let attribute_failed = report["ata_smart_attributes"]["table"]
+        .as_array()
+        .is_some_and(|table| table.iter().any(|attribute| attribute["when_failed"].as_str().is_some_and(|when| !when.is_empty())));
+    Ok(if passed && !attribute_failed { Health::Passed } else { Health::Failed })
+}
+
+/// Checks `device` every `interval`, separately from the disk module's usage polling, and
+/// triggers the module `id` when the verdict changes.
+pub async fn poll(device: PathBuf, sudo: bool, health: SmartHealth, interval: Duration, id: ModuleId, trigger_tx: broadcast::Sender<ModuleId>) {
+    let mut interval = tokio::time::interval(interval);
+    let mut failing = false;
+    loop {
+        interval.tick().await;
+        let verdict = match query(&device, sudo).await {
+            Ok(verdict) => {
+                failing = false;
+                Some(verdict)
+            }
+            Err(e) => {
+                if !std::mem::replace(&mut failing, true) {
+                    tracing::warn!(target: "monitor", module = %id, "Cannot read SMART health of {}: {:#}", device.display(), e);
+                }
+                None
+            }
+        };
+        let previous = std::mem::replace(&mut *health.lock().unwrap(), verdict);
+        if previous != verdict {
+            tracing::info!(target: "monitor", module = %id, ?verdict, "SMART health of {} changed", device.display());
+            let _ = trigger_tx.send(id.clone());
+        }
+    }
+}
@@ -0,0 +1,110 @@
+use crate::registry::ModuleId;
+use crate::util;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The state file is rewritten this often, so a crash loses at most this much.
+const SAVE_INTERVAL: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Saved {
+    text: String,
+    /// When the module produced the value, in Unix seconds.
+    at: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateFile {
+    modules: HashMap<String, Saved>,
+}
+
+/// The last successful value of every module, persisted so slow modules aren't blank for a
+/// while after a restart.
+#[derive(Debug, Clone)]
+pub struct State {
+    path: PathBuf,
+    values: Arc<Mutex<HashMap<ModuleId, Saved>>>,
+}
+
+impl State {
+    /// `None` when the cache directory can't be determined.
+    pub fn new() -> Option<Self> {
+        let cache = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+        Some(State { path: cache.join("dwm-status-bar/state.json"), values: Arc::default() })
+    }
+
+    /// Loads the state file and returns the values of `modules` younger than their maximum age.
+    /// A missing or unreadable file restores nothing.
+    pub fn restore(&self, modules: &[(ModuleId, Duration)]) -> Vec<(ModuleId, String)> {
+        let file: StateFile = match fs::read_to_string(&self.path) {
+            Ok(contents) => match serde_json::from_str(&contents) {
+                Ok(file) => file,
+                Err(e) => {
+                    tracing::warn!("Ignoring corrupt state file {}: {}", self.path.display(), e);
+                    return Vec::new();
+                }
+            },
+            Err(e) => {
+                tracing::debug!("No state restored from {}: {}", self.path.display(), e);
+                return Vec::new();
+            }
+        };
+        let now = unix_now();
+        let mut values = self.values.lock().unwrap();
+        modules
+            .iter()
+            .filter_map(|(id, max_age)| {
+                let saved = file.modules.get(&**id)?;
+                // A timestamp in the future (the clock went back) can't be trusted either.
+                let age = now.checked_sub(saved.at)?;
+                if age > max_age.as_secs() {
+                    return None;
+                }
+                values.insert(id.clone(), saved.clone());
+                tracing::debug!(target: "monitor", "Restored '{}' from {}s ago", id, age);
+                Some((id.clone(), saved.text.clone()))
+            })
+            .collect()
+    }
+
+    pub fn record(&self, id: &ModuleId, text: &str) {
+        self.values.lock().unwrap().insert(id.clone(), Saved { text: text.to_string(), at: unix_now() });
+    }
+
+    fn save(&self) -> Result<()> {
+        let file = StateFile {
+            modules: self.values.lock().unwrap().iter().map(|(id, saved)| (id.to_string(), saved.clone())).collect(),
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        util::write_atomic(&self.path, &serde_json::to_string(&file)?)
+    }
+
+    /// Saves the state, logging instead of failing.
+    pub fn save_or_warn(&self) {
+        if let Err(e) = self.save() {
+            tracing::warn!("Cannot save state to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Saves the state every `SAVE_INTERVAL`. The last save happens in the shutdown sequence.
+pub async fn run(state: State) {
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + SAVE_INTERVAL, SAVE_INTERVAL);
+    loop {
+        interval.tick().await;
+        state.save_or_warn();
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
+}
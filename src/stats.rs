@@ -0,0 +1,73 @@
+use crate::registry::ModuleId;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the execution time histogram buckets.
+pub const DURATION_BUCKETS: [f64; 10] = [0.001, 0.005, 0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Rolling execution statistics for a single module.
+#[derive(Debug, Default, Clone)]
+pub struct ModuleStats {
+    pub runs: u64,
+    pub errors: u64,
+    pub total: Duration,
+    pub max: Duration,
+    pub last: Duration,
+    /// Non-cumulative run counts per `DURATION_BUCKETS` entry; slower runs only count towards `runs`.
+    pub buckets: [u64; DURATION_BUCKETS.len()],
+}
+
+impl ModuleStats {
+    pub fn mean(&self) -> Duration {
+        if self.runs == 0 {
+            Duration::ZERO
+        } else {
+            self.total.div_f64(self.runs as f64)
+        }
+    }
+}
+
+/// Cheap, always-on statistics shared by every monitor task.
+#[derive(Debug, Default, Clone)]
+pub struct Stats(Arc<Mutex<BTreeMap<ModuleId, ModuleStats>>>);
+
+impl Stats {
+    pub fn record(&self, id: &ModuleId, elapsed: Duration, ok: bool) {
+        let mut map = self.0.lock().unwrap();
+        let entry = map.entry(id.clone()).or_default();
+        entry.runs += 1;
+        if !ok {
+            entry.errors += 1;
+        }
+        entry.total += elapsed;
+        entry.max = entry.max.max(elapsed);
+        entry.last = elapsed;
+        if let Some(i) = DURATION_BUCKETS.iter().position(|&le| elapsed.as_secs_f64() <= le) {
+            entry.buckets[i] += 1;
+        }
+    }
+
+    pub fn snapshot(&self) -> Vec<(ModuleId, ModuleStats)> {
+        self.0.lock().unwrap().iter().map(|(id, s)| (id.clone(), s.clone())).collect()
+    }
+
+    pub fn table(&self) -> String {
+        let map = self.0.lock().unwrap();
+        let mut out = format!("{:<14} {:>8} {:>8} {:>10} {:>10} {:>10}", "module", "runs", "errors", "mean_ms", "max_ms", "last_ms");
+        for (id, s) in map.iter() {
+            let _ = write!(
+                out,
+                "\n{:<14} {:>8} {:>8} {:>10.1} {:>10.1} {:>10.1}",
+                id,
+                s.runs,
+                s.errors,
+                s.mean().as_secs_f64() * 1000.0,
+                s.max.as_secs_f64() * 1000.0,
+                s.last.as_secs_f64() * 1000.0,
+            );
+        }
+        out
+    }
+}
@@ -0,0 +1,220 @@
+use crate::bar::Update;
+use crate::health::Outcome;
+use crate::registry::ModuleId;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::{AbortHandle, Id, JoinError, JoinSet};
+use tokio_util::sync::CancellationToken;
+
+/// Restarts allowed within `RESTART_WINDOW` before a module is given up on.
+const MAX_RESTARTS: usize = 5;
+const RESTART_WINDOW: Duration = Duration::from_secs(600);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Builds a fresh instance of a module's task, so it can be started again after a panic.
+pub type TaskFactory = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = ()> + Send>> + Send + Sync>;
+
+struct Supervised {
+    id: ModuleId,
+    factory: TaskFactory,
+    abort: AbortHandle,
+    restarts: VecDeque<Instant>,
+}
+
+enum Command {
+    Spawn(ModuleId, TaskFactory),
+    // Only device hot-plug stops modules, and that is Linux-only.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    Stop(ModuleId),
+    Shutdown(Duration, oneshot::Sender<()>),
+}
+
+/// Owns every module task and restarts the ones that panic, with exponential backoff.
+/// Tasks that return normally (a monitor disabling itself, or the bar shutting down) stay stopped.
+#[derive(Clone)]
+pub struct Supervisor {
+    tx: mpsc::UnboundedSender<Command>,
+}
+
+impl Supervisor {
+    /// Restarts waiting in their backoff give up once `shutdown` is cancelled.
+    pub fn start(update_tx: mpsc::Sender<Update>, shutdown: CancellationToken) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(run(rx, update_tx, shutdown));
+        Supervisor { tx }
+    }
+
+    pub fn spawn(&self, id: ModuleId, factory: TaskFactory) {
+        if self.tx.send(Command::Spawn(id.clone(), factory)).is_err() {
+            tracing::error!("Supervisor is gone, cannot start {}", id);
+        }
+    }
+
+    /// Cancels a module's tasks, e.g. when its device is unplugged, and clears its slot.
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    pub fn stop(&self, id: ModuleId) {
+        if self.tx.send(Command::Stop(id.clone())).is_err() {
+            tracing::error!("Supervisor is gone, cannot stop {}", id);
+        }
+    }
+
+    /// Waits for every task to return, once the shutdown token is cancelled. Tasks still
+    /// running after `timeout` are aborted. Nothing is started or restarted afterwards.
+    pub async fn shutdown(&self, timeout: Duration) {
+        let (reply, done) = oneshot::channel();
+        if self.tx.send(Command::Shutdown(timeout, reply)).is_ok() {
+            let _ = done.await;
+        }
+    }
+}
+
+async fn run(mut rx: mpsc::UnboundedReceiver<Command>, update_tx: mpsc::Sender<Update>, cancel: CancellationToken) {
+    let mut tasks = JoinSet::new();
+    let mut supervised: HashMap<Id, Supervised> = HashMap::new();
+    let mut shutdown: Option<(tokio::time::Instant, oneshot::Sender<()>)> = None;
+    loop {
+        if shutdown.is_some() && tasks.is_empty() {
+            break;
+        }
+        let deadline = shutdown.as_ref().map(|&(deadline, _)| deadline);
+        tokio::select! {
+            Some(command) = rx.recv() => match command {
+                Command::Spawn(id, _) if shutdown.is_some() => tracing::debug!(target: "monitor", "Shutting down, not starting {}", id),
+                Command::Spawn(id, factory) => {
+                    let abort = tasks.spawn(factory());
+                    supervised.insert(abort.id(), Supervised { id, factory, abort, restarts: VecDeque::new() });
+                }
+                Command::Stop(id) => {
+                    // The aborted tasks are removed from `supervised` when they are joined.
+                    for entry in supervised.values().filter(|entry| entry.id == id) {
+                        entry.abort.abort();
+                    }
+                    tracing::info!(target: "monitor", "Stopped monitor '{}'", id);
+                    let _ = update_tx.send(Update::success(id, String::new())).await;
+                }
+                Command::Shutdown(timeout, reply) => {
+                    tracing::info!(target: "monitor", tasks = tasks.len(), "Waiting for monitors to stop");
+                    shutdown = Some((tokio::time::Instant::now() + timeout, reply));
+                }
+            },
+            Some(joined) = tasks.join_next_with_id() => {
+                let (task_id, error) = match joined {
+                    Ok((task_id, ())) => (task_id, None),
+                    Err(e) => (e.id(), Some(e)),
+                };
+                let Some(mut entry) = supervised.remove(&task_id) else { continue };
+                let Some(error) = error.filter(JoinError::is_panic) else { continue };
+                tracing::error!(target: "monitor", "Monitor '{}' panicked: {}", entry.id, panic_message(error));
+                if shutdown.is_some() {
+                    continue;
+                }
+
+                let now = Instant::now();
+                entry.restarts.retain(|t| now.duration_since(*t) < RESTART_WINDOW);
+                if entry.restarts.len() >= MAX_RESTARTS {
+                    let reason = format!("panicked {} times in {:?}", MAX_RESTARTS + 1, RESTART_WINDOW);
+                    tracing::warn!(target: "monitor", module = %entry.id, reason = %reason, "Monitor permanently disabled");
+                    let _ = update_tx.send(Update::new(entry.id.clone(), Outcome::Stopped(format!("{}: failed", entry.id)))).await;
+                    continue;
+                }
+                entry.restarts.push_back(now);
+                let backoff = Duration::from_secs(1 << (entry.restarts.len() - 1)).min(MAX_BACKOFF);
+                tracing::warn!(target: "monitor", "Restarting monitor '{}' in {:?}", entry.id, backoff);
+                let (factory, cancel) = (entry.factory.clone(), cancel.clone());
+                entry.abort = tasks.spawn(async move {
+                    tokio::select! {
+                        _ = cancel.cancelled() => return,
+                        _ = tokio::time::sleep(backoff) => {}
+                    }
+                    factory().await;
+                });
+                supervised.insert(entry.abort.id(), entry);
+            }
+            _ = tokio::time::sleep_until(deadline.unwrap_or_else(tokio::time::Instant::now)), if deadline.is_some() => {
+                tracing::warn!(target: "monitor", tasks = tasks.len(), "Monitors didn't stop in time, aborting them");
+                tasks.shutdown().await;
+                break;
+            }
+            else => break,
+        }
+    }
+    if let Some((_, reply)) = shutdown {
+        let _ = reply.send(());
+    }
+}
+
+fn panic_message(error: JoinError) -> String {
+    let payload = error.into_panic();
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "non-string panic payload".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+
+    #[tokio::test(start_paused = true)]
+    async fn panicked_monitor_comes_back_after_the_backoff() {
+        let (update_tx, mut update_rx) = mpsc::channel(8);
+        let supervisor = Supervisor::start(update_tx.clone(), CancellationToken::new());
+        let started = tokio::time::Instant::now();
+        let runs = Arc::new(AtomicUsize::new(0));
+        // Like a monitor loop whose second run panics.
+        supervisor.spawn(
+            "flaky".into(),
+            Arc::new(move || {
+                let (runs, update_tx) = (runs.clone(), update_tx.clone());
+                Box::pin(async move {
+                    loop {
+                        let run = runs.fetch_add(1, Ordering::SeqCst) + 1;
+                        assert_ne!(run, 2, "second run fails");
+                        let _ = update_tx.send(Update::success("flaky".into(), format!("run {}", run))).await;
+                        tokio::time::sleep(Duration::from_secs(10)).await;
+                    }
+                })
+            }),
+        );
+
+        let first = update_rx.recv().await.unwrap();
+        assert!(matches!(first.outcome, Outcome::Success(ref text) if text == "run 1"));
+        let restarted = update_rx.recv().await.unwrap();
+        assert!(matches!(restarted.outcome, Outcome::Success(ref text) if text == "run 3"));
+        // The panic at 10s plus the first restart's 1s backoff.
+        assert_eq!(started.elapsed().as_secs(), 11);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn panicking_task_backs_off_then_is_given_up() {
+        let (update_tx, mut update_rx) = mpsc::channel(8);
+        let supervisor = Supervisor::start(update_tx, CancellationToken::new());
+        let started = tokio::time::Instant::now();
+        let runs: Arc<Mutex<Vec<Duration>>> = Arc::default();
+        let task_runs = runs.clone();
+        supervisor.spawn(
+            "crashy".into(),
+            Arc::new(move || {
+                task_runs.lock().unwrap().push(started.elapsed());
+                Box::pin(async { panic!("boom") })
+            }),
+        );
+
+        let update = update_rx.recv().await.unwrap();
+        assert_eq!(&*update.id, "crashy");
+        assert!(matches!(update.outcome, Outcome::Stopped(ref text) if text == "crashy: failed"));
+        let gaps: Vec<u64> = runs.lock().unwrap().windows(2).map(|pair| (pair[1] - pair[0]).as_secs()).collect();
+        assert_eq!(gaps, [1, 2, 4, 8, 16]);
+
+        // Given up for good: nothing runs again later.
+        tokio::time::sleep(Duration::from_secs(600)).await;
+        assert_eq!(runs.lock().unwrap().len(), MAX_RESTARTS + 1);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::registry::ModuleId;
+use crate::stats::Stats;
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use sysinfo::{CpuExt, DiskExt, ProcessExt, System, SystemExt};
+use tokio::sync::{mpsc, oneshot};
+
+/// Space on one mounted filesystem, in bytes, and its type, e.g. `ext4`.
+#[derive(Debug, Clone)]
+pub struct DiskUsage {
+    pub total: u64,
+    pub available: u64,
+    pub fs_type: String,
+}
+
+/// Memory usage, in bytes. `used` leaves out page cache and other reclaimable memory, `free`
+/// is memory not used for anything, cache included.
+#[derive(Debug, Clone, Copy)]
+pub struct MemUsage {
+    pub used: u64,
+    pub free: u64,
+    pub total: u64,
+}
+
+/// The processes using the most CPU (percent of one core, since the previous process request)
+/// and the most resident memory (bytes). Kernel threads are left out.
+#[derive(Debug, Clone, Default)]
+pub struct Hogs {
+    pub cpu: Option<(String, f32)>,
+    pub memory: Option<(String, u64)>,
+}
+
+enum Request {
+    Disk(PathBuf, oneshot::Sender<Option<DiskUsage>>),
+    Mem(oneshot::Sender<MemUsage>),
+    /// Global CPU usage in percent since the previous CPU request.
+    Cpu(oneshot::Sender<f32>),
+    Hogs(oneshot::Sender<Hogs>),
+}
+
+/// Handle to the task that owns the `sysinfo::System`. Monitors ask it for readings instead of
+/// locking a shared `System`, so slow refreshes never block a tokio worker thread.
+#[derive(Clone)]
+pub struct SystemHandle {
+    tx: mpsc::Sender<Request>,
+}
+
+impl SystemHandle {
+    /// Starts the actor on its own thread. Refresh times are recorded under the `sysinfo` stats entry.
+    /// CPU usage is measured over `cpu_sample_period`, and never less than sysinfo allows.
+    pub fn spawn(stats: Stats, profile: bool, cpu_sample_period: Duration) -> Result<Self> {
+        let (tx, rx) = mpsc::channel(32);
+        let cpu_sample_period = cpu_sample_period.max(System::MINIMUM_CPU_UPDATE_INTERVAL);
+        std::thread::Builder::new().name("sysinfo".into()).spawn(move || run(rx, stats, profile, cpu_sample_period))?;
+        Ok(SystemHandle { tx })
+    }
+
+    pub async fn disk(&self, mount: PathBuf) -> Result<DiskUsage> {
+        let display = mount.display().to_string();
+        self.request(|reply| Request::Disk(mount, reply)).await?.ok_or_else(|| anyhow!("'{}' disk not found", display))
+    }
+
+    pub async fn memory(&self) -> Result<MemUsage> {
+        self.request(Request::Mem).await
+    }
+
+    pub async fn cpu(&self) -> Result<f32> {
+        self.request(Request::Cpu).await
+    }
+
+    /// Refreshes the process list, the most expensive reading, so only the `hog` module asks for it.
+    pub async fn hogs(&self) -> Result<Hogs> {
+        self.request(Request::Hogs).await
+    }
+
+    async fn request<T>(&self, make: impl FnOnce(oneshot::Sender<T>) -> Request) -> Result<T> {
+        let (reply, rx) = oneshot::channel();
+        self.tx.send(make(reply)).await.map_err(|_| anyhow!("sysinfo task stopped"))?;
+        rx.await.map_err(|_| anyhow!("sysinfo task dropped the request"))
+    }
+}
+
+/// Serves requests in batches: everything queued by the time a batch starts shares one refresh
+/// of each kind. CPU requests are answered last, once the sample period has passed, so the other
+/// requests in the batch don't wait for it.
+fn run(mut rx: mpsc::Receiver<Request>, stats: Stats, profile: bool, cpu_sample_period: Duration) {
+    let id: ModuleId = "sysinfo".into();
+    let mut sys = System::new();
+
+    while let Some(first) = rx.blocking_recv() {
+        let mut batch = vec![first];
+        while let Ok(request) = rx.try_recv() {
+            batch.push(request);
+        }
+
+        let mut cpu = Vec::new();
+        let batch: Vec<Request> = batch
+            .into_iter()
+            .filter_map(|request| match request {
+                Request::Cpu(reply) => {
+                    cpu.push(reply);
+                    None
+                }
+                request => Some(request),
+            })
+            .collect();
+        let start = Instant::now();
+        if !cpu.is_empty() {
+            // Usage is measured between two refreshes. Taking both in this batch keeps the window
+            // at `cpu_sample_period` rather than the time since the last batch.
+            sys.refresh_cpu();
+        }
+        if batch.iter().any(|r| matches!(r, Request::Disk(..))) {
+            sys.refresh_disks_list();
+        }
+        if batch.iter().any(|r| matches!(r, Request::Mem(_))) {
+            sys.refresh_memory();
+        }
+        if batch.iter().any(|r| matches!(r, Request::Hogs(_))) {
+            sys.refresh_processes();
+        }
+        let mut refresh_time = start.elapsed();
+        let requests = batch.len() + cpu.len();
+
+        for request in batch {
+            match request {
+                Request::Disk(mount, reply) => {
+                    let usage = sys.disks().iter().find(|d| d.mount_point() == mount).map(|d| DiskUsage {
+                        total: d.total_space(),
+                        available: d.available_space(),
+                        fs_type: String::from_utf8_lossy(d.file_system()).into_owned(),
+                    });
+                    let _ = reply.send(usage);
+                }
+                Request::Mem(reply) => {
+                    let _ = reply.send(MemUsage { used: sys.used_memory(), free: sys.free_memory(), total: sys.total_memory() });
+                }
+                Request::Hogs(reply) => {
+                    let _ = reply.send(hogs(&sys));
+                }
+                Request::Cpu(_) => unreachable!("CPU requests are answered after the sample period"),
+            }
+        }
+
+        if !cpu.is_empty() {
+            std::thread::sleep(cpu_sample_period.saturating_sub(start.elapsed()));
+            let refreshed = Instant::now();
+            sys.refresh_cpu();
+            refresh_time += refreshed.elapsed();
+            let usage = sys.global_cpu_info().cpu_usage();
+            for reply in cpu {
+                let _ = reply.send(usage);
+            }
+        }
+        stats.record(&id, refresh_time, true);
+        if profile {
+            tracing::info!(target: "monitor", module = %id, duration_ms = refresh_time.as_millis(), requests, "Refreshed system info");
+        }
+    }
+}
+
+fn hogs(sys: &System) -> Hogs {
+    // Kernel threads have no command line.
+    let processes: Vec<_> = sys.processes().values().filter(|p| !p.cmd().is_empty()).collect();
+    Hogs {
+        cpu: processes
+            .iter()
+            .max_by(|a, b| a.cpu_usage().total_cmp(&b.cpu_usage()))
+            .map(|p| (p.name().to_string(), p.cpu_usage())),
+        memory: processes.iter().max_by_key(|p| p.memory()).map(|p| (p.name().to_string(), p.memory())),
+    }
+}
@@ -0,0 +1,204 @@
+use crate::config::{TickerConfig, TickerSymbol};
+use crate::util::truncate;
+use anyhow::{Context, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const COINGECKO_URL: &str = "https://api.coingecko.com/api/v3/simple/price";
+/// Quotes older than this are shown with a `*` marker.
+const STALE_AFTER: Duration = Duration::from_secs(3600);
+
+#[derive(Debug, Clone, Copy)]
+struct Quote {
+    price: f64,
+    change_24h: Option<f64>,
+}
+
+/// Price ticker state: the shared HTTP client plus the last good quote per symbol, which is
+/// kept through network outages.
+pub struct Ticker {
+    client: reqwest::Client,
+    config: TickerConfig,
+    last: Mutex<HashMap<String, (Quote, Instant)>>,
+}
+
+impl Ticker {
+    pub fn new(client: reqwest::Client, config: TickerConfig) -> Self {
+        Ticker { client, config, last: Mutex::new(HashMap::new()) }
+    }
+
+    async fn fetch(&self) -> Result<HashMap<String, Quote>> {
+        let mut quotes = HashMap::new();
+        let coingecko: Vec<&str> = self.config.symbols.iter().filter(|s| s.url.is_none()).map(|s| s.id.as_str()).collect();
+        if !coingecko.is_empty() {
+            let currency = &self.config.vs_currency;
+            let body: Value = self
+                .client
+                .get(COINGECKO_URL)
+                .query(&[("ids", coingecko.join(",").as_str()), ("vs_currencies", currency), ("include_24hr_change", "true")])
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            for id in coingecko {
+                if let Some(price) = body[id][currency].as_f64() {
+                    let change_24h = body[id][format!("{}_24h_change", currency)].as_f64();
+                    quotes.insert(id.to_string(), Quote { price, change_24h });
+                }
+            }
+        }
+        for symbol in self.config.symbols.iter().filter(|s| s.url.is_some()) {
+            match self.fetch_custom(symbol).await {
+                Ok(quote) => {
+                    quotes.insert(symbol.id.clone(), quote);
+                }
+                Err(e) => tracing::warn!(target: "monitor::ticker", "Fetching {} failed: {}", symbol.id, e),
+            }
+        }
+        Ok(quotes)
+    }
+
+    async fn fetch_custom(&self, symbol: &TickerSymbol) -> Result<Quote> {
+        let url = symbol.url.as_deref().unwrap_or_default();
+        let body: Value = self.client.get(url).send().await?.error_for_status()?.json().await?;
+        let price = json_number(&body, &symbol.price_pointer).context("price not found in response")?;
+        let change_24h = symbol.change_pointer.as_deref().and_then(|p| json_number(&body, p));
+        Ok(Quote { price, change_24h })
+    }
+}
+
+/// Reads a number at a JSON pointer or dot path, accepting numeric strings as well
+/// (`{"amount": "67187.5"}`).
+fn json_number(body: &Value, path: &str) -> Option<f64> {
+    let value = if path.is_empty() || path.starts_with('/') {
+        body.pointer(path)?
+    } else {
+        path.split('.').try_fold(body, |value, key| match value {
+            Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => value.get(key),
+        })?
+    };
+    value.as_f64().or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+/// Formats a quote as `BTC 67.2k ▲1.3%`.
+fn format_quote(label: &str, price: f64, change_24h: Option<f64>) -> String {
+    let price = compact_number(price);
+    match change_24h {
+        Some(change) => format!("{} {} {}", label, price, format_change(change)),
+        None => format!("{} {}", label, price),
+    }
+}
+
+/// Fills a symbol's `format`, e.g. `₿ ${value}` becomes `₿ $67.2k`.
+fn format_custom(format: &str, price: f64, change_24h: Option<f64>) -> String {
+    format.replace("{value}", &compact_number(price)).replace("{change}", &change_24h.map(format_change).unwrap_or_default())
+}
+
+fn format_change(change: f64) -> String {
+    let arrow = if change >= 0.0 { '▲' } else { '▼' };
+    format!("{}{:.1}%", arrow, change.abs())
+}
+
+/// Shortens large numbers with k/M/B suffixes and keeps a sensible precision for small ones.
+/// Each range ends where rounding would carry into the next unit, so `999_950` is `1.0M`
+/// rather than `1000.0k`.
+fn compact_number(value: f64) -> String {
+    let abs = value.abs();
+    if abs >= 999_950_000.0 {
+        format!("{:.1}B", value / 1e9)
+    } else if abs >= 999_950.0 {
+        format!("{:.1}M", value / 1e6)
+    } else if abs >= 999.995 {
+        format!("{:.1}k", value / 1e3)
+    } else if abs >= 0.99995 {
+        format!("{:.2}", value)
+    } else {
+        format!("{:.4}", value)
+    }
+}
+
+pub async fn monitor(ticker: &Ticker) -> Result<String> {
+    match ticker.fetch().await {
+        Ok(quotes) => {
+            let now = Instant::now();
+            let mut last = ticker.last.lock().unwrap();
+            for (id, quote) in quotes {
+                last.insert(id, (quote, now));
+            }
+        }
+        Err(e) => tracing::warn!(target: "monitor::ticker", "Fetching quotes failed, keeping last values: {}", e),
+    }
+
+    let last = ticker.last.lock().unwrap();
+    let parts: Vec<String> = ticker
+        .config
+        .symbols
+        .iter()
+        .filter_map(|symbol| {
+            let (quote, fetched) = last.get(&symbol.id)?;
+            let mut text = match &symbol.format {
+                Some(format) => format_custom(format, quote.price, quote.change_24h).trim().to_string(),
+                None => format_quote(symbol.label.as_deref().unwrap_or(&symbol.id), quote.price, quote.change_24h),
+            };
+            if fetched.elapsed() > STALE_AFTER {
+                text.push('*');
+            }
+            Some(text)
+        })
+        .collect();
+    Ok(truncate(&parts.join(" "), ticker.config.max_width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn compact_number_suffixes() {
+        assert_eq!(compact_number(67_187.5), "67.2k");
+        assert_eq!(compact_number(1_234_567.0), "1.2M");
+        assert_eq!(compact_number(2_500_000_000.0), "2.5B");
+        assert_eq!(compact_number(-45_300.0), "-45.3k");
+        assert_eq!(compact_number(950.0), "950.00");
+        assert_eq!(compact_number(42.1234), "42.12");
+        assert_eq!(compact_number(0.000123), "0.0001");
+    }
+
+    #[test]
+    fn compact_number_rounds_before_picking_a_suffix() {
+        assert_eq!(compact_number(999_950.0), "1.0M");
+        assert_eq!(compact_number(999_949.0), "999.9k");
+        assert_eq!(compact_number(999_999_999.0), "1.0B");
+        assert_eq!(compact_number(999.996), "1.0k");
+        assert_eq!(compact_number(999.994), "999.99");
+        assert_eq!(compact_number(0.99996), "1.00");
+    }
+
+    #[test]
+    fn quotes_with_and_without_change() {
+        assert_eq!(format_quote("BTC", 67_187.5, Some(1.31)), "BTC 67.2k ▲1.3%");
+        assert_eq!(format_quote("ETH", 3_210.0, Some(-0.04)), "ETH 3.2k ▼0.0%");
+        assert_eq!(format_quote("AAPL", 187.25, None), "AAPL 187.25");
+    }
+
+    #[test]
+    fn custom_formats() {
+        assert_eq!(format_custom("₿ ${value} {change}", 67_187.5, Some(-2.25)), "₿ $67.2k ▼2.2%");
+        assert_eq!(format_custom("₿ ${value} {change}", 67_187.5, None), "₿ $67.2k ");
+    }
+
+    #[test]
+    fn json_numbers_by_pointer_or_path() {
+        let body = json!({"data": {"amount": "67187.5", "rates": [{"change": -1.5}]}});
+        assert_eq!(json_number(&body, "/data/amount"), Some(67_187.5));
+        assert_eq!(json_number(&body, "data.amount"), Some(67_187.5));
+        assert_eq!(json_number(&body, "data.rates.0.change"), Some(-1.5));
+        assert_eq!(json_number(&body, "data.missing"), None);
+        assert_eq!(json_number(&json!({"price": "n/a"}), "price"), None);
+    }
+}
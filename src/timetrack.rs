@@ -0,0 +1,69 @@
+use crate::config::TimeTool;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+
+/// The running timer of a time tracker.
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub name: String,
+    pub start: DateTime<Local>,
+}
+
+/// The configured tool, or the first of timewarrior and watson that is installed.
+pub fn detect(configured: Option<TimeTool>) -> Option<TimeTool> {
+    configured.or_else(|| {
+        [(TimeTool::Timew, "timew"), (TimeTool::Watson, "watson")]
+            .into_iter()
+            .find(|(_, cmd)| crate::command_exists(cmd))
+            .map(|(tool, _)| tool)
+    })
+}
+
+/// Asks the tracker for the running timer. `None` when nothing is being tracked.
+pub async fn fetch(tool: TimeTool) -> Result<Option<Session>> {
+    let session = match tool {
+        TimeTool::Timew => {
+            if crate::run_command("timew", &["get", "dom.active"]).await?.trim() != "1" {
+                return Ok(None);
+            }
+            let name = crate::run_command("timew", &["get", "dom.active.tag.1"]).await?.trim().to_string();
+            let start = crate::run_command("timew", &["get", "dom.active.start"]).await?;
+            Session { name, start: parse_timew_start(start.trim())? }
+        }
+        TimeTool::Watson => match parse_watson_status(&crate::run_command("watson", &["status"]).await?) {
+            Some(session) => session,
+            None => return Ok(None),
+        },
+    };
+    tracing::debug!(target: "monitor::timetrack", name = %session.name, start = %session.start, "Fetched running timer");
+    Ok(Some(session))
+}
+
+/// `dom.active.start` is local time, e.g. `2024-06-07T12:00:00`.
+fn parse_timew_start(start: &str) -> Result<DateTime<Local>> {
+    if let Ok(start) = DateTime::parse_from_rfc3339(start) {
+        return Ok(start.with_timezone(&Local));
+    }
+    let naive = NaiveDateTime::parse_from_str(start, "%Y-%m-%dT%H:%M:%S").with_context(|| format!("Unexpected timew start '{}'", start))?;
+    Local.from_local_datetime(&naive).earliest().with_context(|| format!("Nonexistent local time '{}'", start))
+}
+
+/// Parses `Project crate-refactor [tag] started 5 minutes ago (2024.06.07 12:00:00+0200)`.
+/// `No project started.` and anything else unrecognised mean no timer.
+fn parse_watson_status(output: &str) -> Option<Session> {
+    let rest = output.trim().strip_prefix("Project ")?;
+    let name = rest.split_whitespace().next()?.to_string();
+    let stamp = rest.rsplit_once('(')?.1.strip_suffix(')')?;
+    let start = DateTime::parse_from_str(stamp, "%Y.%m.%d %H:%M:%S%z").ok()?.with_timezone(&Local);
+    Some(Session { name, start })
+}
+
+/// Shows the running timer with the time elapsed at `now`, e.g. `⏲ crate-refactor 1:24`.
+/// Hidden when nothing is being tracked.
+pub fn render(session: Option<&Session>, now: DateTime<Local>) -> String {
+    let Some(session) = session else {
+        return String::new();
+    };
+    let minutes = (now - session.start).num_minutes().max(0);
+    format!("⏲ {} {}:{:02}", session.name, minutes / 60, minutes % 60)
+}
@@ -0,0 +1,99 @@
+use crate::config::UpsConfig;
+use anyhow::{bail, Context, Result};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+
+const NUT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A UPS monitored by upsd. `on_battery` lets the caller poll faster during an outage.
+pub struct Ups {
+    config: UpsConfig,
+    name: String,
+    pub on_battery: AtomicBool,
+}
+
+impl Ups {
+    pub fn new(config: UpsConfig, name: String) -> Self {
+        Ups { config, name, on_battery: AtomicBool::new(false) }
+    }
+
+    /// Reads every variable of the UPS, first over the NUT protocol and then through `upsc`.
+    async fn vars(&self) -> Result<HashMap<String, String>> {
+        match timeout(NUT_TIMEOUT, self.list_vars()).await.unwrap_or_else(|_| Err(anyhow::anyhow!("upsd timed out"))) {
+            Ok(vars) => Ok(vars),
+            Err(e) => {
+                tracing::debug!(target: "monitor::ups", "upsd query failed, falling back to upsc: {}", e);
+                let target = format!("{}@{}:{}", self.name, self.config.host, self.config.port);
+                let output = crate::run_command("upsc", &[&target]).await?;
+                Ok(parse_upsc(&output))
+            }
+        }
+    }
+
+    async fn list_vars(&self) -> Result<HashMap<String, String>> {
+        let stream = TcpStream::connect((self.config.host.as_str(), self.config.port)).await?;
+        let (reader, mut writer) = stream.into_split();
+        writer.write_all(format!("LIST VAR {}\n", self.name).as_bytes()).await?;
+        let mut lines = BufReader::new(reader).lines();
+        let mut vars = HashMap::new();
+        while let Some(line) = lines.next_line().await? {
+            if let Some(err) = line.strip_prefix("ERR ") {
+                bail!("upsd: {}", err);
+            }
+            if line.starts_with("END LIST VAR") {
+                let _ = writer.write_all(b"LOGOUT\n").await;
+                return Ok(vars);
+            }
+            if let Some((key, value)) = parse_var_line(&line) {
+                vars.insert(key, value);
+            }
+        }
+        bail!("upsd closed the connection mid-list")
+    }
+}
+
+/// Parses `VAR <ups> <key> "<value>"`, unescaping `\"` and `\\` in the value.
+fn parse_var_line(line: &str) -> Option<(String, String)> {
+    let mut parts = line.strip_prefix("VAR ")?.splitn(3, ' ');
+    let _ups = parts.next()?;
+    let key = parts.next()?;
+    let quoted = parts.next()?.strip_prefix('"')?.strip_suffix('"')?;
+    let mut value = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        value.push(if c == '\\' { chars.next()? } else { c });
+    }
+    Some((key.to_string(), value))
+}
+
+/// Parses `upsc` output (`key: value` per line).
+fn parse_upsc(output: &str) -> HashMap<String, String> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+pub async fn monitor(ups: &Ups) -> Result<String> {
+    let vars = ups.vars().await?;
+    let status = vars.get("ups.status").context("ups.status missing")?;
+    let on_battery = status.split_whitespace().any(|flag| flag == "OB");
+    ups.on_battery.store(on_battery, Ordering::Relaxed);
+
+    let state = if on_battery { "OB" } else { status.split_whitespace().next().unwrap_or("?") };
+    let mut text = format!("ups: {}", state);
+    if let Some(charge) = vars.get("battery.charge").and_then(|c| c.parse::<f32>().ok()) {
+        text.push_str(&format!(" {:.0}%", charge));
+    }
+    if on_battery {
+        if let Some(runtime) = vars.get("battery.runtime").and_then(|r| r.parse::<f32>().ok()) {
+            text.push_str(&format!(" {:.0}m", runtime / 60.0));
+        }
+    }
+    Ok(text)
+}
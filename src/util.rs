@@ -0,0 +1,81 @@
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+
+/// Truncates `s` to at most `max_chars` characters, replacing the tail with a single `…`.
+/// Works on character boundaries, so multi-byte text and emoji are never split.
+pub fn truncate(s: &str, max_chars: usize) -> String {
+    if s.chars().count() <= max_chars {
+        return s.to_string();
+    }
+    if max_chars == 0 {
+        return String::new();
+    }
+    let end = s.char_indices().nth(max_chars - 1).map(|(i, _)| i).unwrap_or(s.len());
+    format!("{}…", &s[..end])
+}
+
+/// Renders `pct` (clamped to 100) as a `width`-character bar of filled and empty blocks,
+/// e.g. `▓▓▓▓░` for 82% at width 5.
+pub fn percent_to_bar(pct: u8, width: u8) -> String {
+    let filled = ((pct.min(100) as f32 / 100.0) * width as f32).round() as usize;
+    format!("{}{}", "▓".repeat(filled), "░".repeat(width as usize - filled))
+}
+
+/// Renders `values` as one block character each, scaled from `min` (`▁`) to `max` (`█`).
+/// Values outside the range are clamped.
+pub fn sparkline(values: &[f32], min: f32, max: f32) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let range = max - min;
+    values
+        .iter()
+        .map(|&v| {
+            let level = if range > 0.0 { ((v - min) / range).clamp(0.0, 1.0) } else { 0.0 };
+            BLOCKS[(level * (BLOCKS.len() - 1) as f32).round() as usize]
+        })
+        .collect()
+}
+
+/// Writes to a temporary file next to `path` and renames it over the target, so readers never
+/// see a partially written file.
+pub fn write_atomic(path: &Path, contents: &str) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn truncate_keeps_short_text() {
+        assert_eq!(truncate("cpu: 5%", 7), "cpu: 5%");
+        assert_eq!(truncate("", 0), "");
+    }
+
+    #[test]
+    fn truncate_multibyte_on_char_boundaries() {
+        assert_eq!(truncate("Größenänderung", 6), "Größe…");
+        assert_eq!(truncate("日本語のテキスト", 4), "日本語…");
+        assert_eq!(truncate("abc", 0), "");
+    }
+
+    #[test]
+    fn truncate_emoji() {
+        assert_eq!(truncate("🔋🔌⚡🌧☀", 3), "🔋🔌…");
+        assert_eq!(truncate("🔋🔌", 1), "…");
+        assert_eq!(truncate("🔋🔌", 2), "🔋🔌");
+    }
+
+    #[test]
+    fn bar_edges() {
+        assert_eq!(percent_to_bar(0, 5), "░░░░░");
+        assert_eq!(percent_to_bar(100, 5), "▓▓▓▓▓");
+        assert_eq!(percent_to_bar(250, 5), "▓▓▓▓▓");
+        assert_eq!(percent_to_bar(82, 5), "▓▓▓▓░");
+        assert_eq!(percent_to_bar(50, 0), "");
+    }
+}
@@ -0,0 +1,181 @@
+use crate::config::Config;
+use crate::registry::{ModuleId, ModuleRegistry};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// Trigger file prefixes that change visibility instead of refreshing, e.g. `hide.cpu_load`.
+const ACTIONS: [&str; 3] = ["hide", "show", "toggle"];
+
+/// Modules hidden at runtime. Hidden modules keep running; they are only left out of the bar.
+/// Not persisted, so a restart shows everything again.
+#[derive(Clone)]
+pub struct Visibility {
+    hidden: Arc<Mutex<HashSet<ModuleId>>>,
+    changed: Arc<Notify>,
+    /// Modules that stay visible on `hide.all`.
+    minimal: Arc<HashSet<ModuleId>>,
+}
+
+impl Visibility {
+    pub fn new(registry: &ModuleRegistry, minimal: &[String]) -> Self {
+        Visibility {
+            hidden: Arc::default(),
+            changed: Arc::default(),
+            minimal: Arc::new(minimal.iter().filter_map(|id| registry.get(id)).collect()),
+        }
+    }
+
+    pub fn hidden(&self) -> HashSet<ModuleId> {
+        self.hidden.lock().unwrap().clone()
+    }
+
+    /// Resolves once visibility has changed since the last call.
+    pub async fn changed(&self) {
+        self.changed.notified().await;
+    }
+
+    /// Handles a `<action>.<module>` or `<action>.all` trigger name. Returns false if `name`
+    /// isn't a visibility command.
+    pub fn handle(&self, name: &str, registry: &ModuleRegistry) -> bool {
+        let Some((action, target)) = name.split_once('.').filter(|(action, _)| ACTIONS.contains(action)) else {
+            return false;
+        };
+        let targets: Vec<ModuleId> = if target == crate::TRIGGER_ALL {
+            registry.order().iter().filter(|id| !self.minimal.contains(*id)).cloned().collect()
+        } else if let Some(id) = registry.get(target) {
+            vec![id]
+        } else {
+            tracing::warn!("Visibility trigger for unknown module '{}'", target);
+            return true;
+        };
+        let mut hidden = self.hidden.lock().unwrap();
+        let hide = match action {
+            "hide" => true,
+            "show" => false,
+            // Toggling a group hides it unless all of it is already hidden.
+            _ => !targets.iter().all(|id| hidden.contains(id)),
+        };
+        for id in targets {
+            if hide {
+                hidden.insert(id);
+            } else {
+                hidden.remove(&id);
+            }
+        }
+        tracing::info!("Hidden modules: {:?}", hidden);
+        self.changed.notify_one();
+        true
+    }
+}
+
+/// Removes visibility trigger files left over from a previous run, so the bar starts with every
+/// module visible.
+pub fn clear_stale(dir: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let is_visibility = name.to_str().and_then(|n| n.split_once('.')).is_some_and(|(action, _)| ACTIONS.contains(&action));
+        if is_visibility {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+/// `hide_when` rules from the module configs, compiled once at startup.
+pub struct HideRules {
+    /// (module to hide, module to watch, pattern for the watched module's text)
+    rules: Vec<(ModuleId, ModuleId, Regex)>,
+}
+
+impl HideRules {
+    pub fn new(config: &Config, registry: &ModuleRegistry) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (id, module) in &config.modules {
+            let Some(target) = registry.get(id) else { continue };
+            for (watched, pattern) in &module.hide_when {
+                let regex = Regex::new(pattern).with_context(|| format!("Invalid hide_when pattern for '{}'", id))?;
+                match registry.get(watched) {
+                    Some(watched) => rules.push((target.clone(), watched, regex)),
+                    None => tracing::warn!("hide_when for '{}' refers to unknown module '{}'", id, watched),
+                }
+            }
+        }
+        Ok(HideRules { rules })
+    }
+
+    /// Adds the modules whose rules match the current results to `hidden`.
+    pub fn apply(&self, results: &HashMap<ModuleId, String>, hidden: &mut HashSet<ModuleId>) {
+        for (target, watched, regex) in &self.rules {
+            if results.get(watched).is_some_and(|text| regex.is_match(text)) {
+                hidden.insert(target.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ModuleConfig;
+
+    fn hours(toml: &str) -> ModuleConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn visible_hours_parse_as_ranges() {
+        let module = hours("visible_hours = [[8, 12], [13, 18]]");
+        assert_eq!(module.visible_hours, [[8, 12], [13, 18]]);
+        assert!(toml::from_str::<ModuleConfig>("visible_hours = [[8]]").is_err());
+        assert!(toml::from_str::<ModuleConfig>("visible_hours = [[8, -1]]").is_err());
+    }
+
+    #[test]
+    fn visible_hours_are_half_open() {
+        let module = hours("visible_hours = [[8, 12], [13, 18]]");
+        assert!(!module.visible_at(7));
+        assert!(module.visible_at(8));
+        assert!(module.visible_at(11));
+        assert!(!module.visible_at(12));
+        assert!(module.visible_at(17));
+        assert!(!module.visible_at(18));
+    }
+
+    #[test]
+    fn visible_hours_wrap_past_midnight() {
+        let module = hours("visible_hours = [[22, 6]]");
+        assert!(module.visible_at(22));
+        assert!(module.visible_at(23));
+        assert!(module.visible_at(0));
+        assert!(module.visible_at(5));
+        assert!(!module.visible_at(6));
+        assert!(!module.visible_at(12));
+        assert!(!module.visible_at(21));
+    }
+
+    #[test]
+    fn no_visible_hours_means_always() {
+        let module = hours("");
+        assert!((0..24).all(|hour| module.visible_at(hour)));
+    }
+
+    #[test]
+    fn hide_show_and_toggle_triggers() {
+        let registry = ModuleRegistry::new(["cpu_load", "ram", "datetime"]);
+        let visibility = Visibility::new(&registry, &["datetime".to_string()]);
+        let hidden = |names: &[&str]| names.iter().map(|&id| ModuleId::from(id)).collect::<HashSet<_>>();
+        assert!(!visibility.handle("cpu_load", &registry));
+        assert!(visibility.handle("hide.ram", &registry));
+        assert_eq!(visibility.hidden(), hidden(&["ram"]));
+        assert!(visibility.handle("toggle.all", &registry));
+        assert_eq!(visibility.hidden(), hidden(&["cpu_load", "ram"]));
+        assert!(visibility.handle("toggle.all", &registry));
+        assert_eq!(visibility.hidden(), hidden(&[]));
+        assert!(visibility.handle("show.nope", &registry));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::config::VolumeConfig;
+use crate::registry::ModuleId;
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::broadcast;
+use zbus::fdo::PropertiesProxy;
+use zbus::names::InterfaceName;
+use zbus::zvariant::OwnedObjectPath;
+use zbus::{connection, message, Connection, MessageStream};
+
+/// Sink name that follows the sound server's current default sink.
+const DEFAULT_SINK: &str = "default";
+/// Characters of the sink name shown with `show_sink_name`.
+const SINK_NAME_LEN: usize = 4;
+/// PulseAudio signals that change what the module shows. Changing the fallback sink switches
+/// which sink `default` reads.
+const PULSE_SIGNALS: &[&str] = &[
+    "org.PulseAudio.Core1.Device.VolumeUpdated",
+    "org.PulseAudio.Core1.Device.MuteUpdated",
+    "org.PulseAudio.Core1.FallbackSinkUpdated",
+];
+
+/// `SNDRV_CTL_IOCTL_SUBSCRIBE_EVENTS`, `_IOWR('U', 0x16, int)`.
+#[cfg(target_os = "linux")]
+const CTL_SUBSCRIBE_EVENTS: libc::Ioctl = 0xC004_5516_u32 as libc::Ioctl;
+/// Size of `struct snd_ctl_event`: the event type, then the changed element's mask and ID.
+#[cfg(target_os = "linux")]
+const CTL_EVENT_SIZE: usize = 64;
+/// `SNDRV_CTL_EVENT_ELEM`, the only event type there is.
+#[cfg(target_os = "linux")]
+const CTL_EVENT_ELEM: u32 = 0;
+/// `SNDRV_CTL_EVENT_MASK_VALUE`, set when a control's value changed.
+#[cfg(target_os = "linux")]
+const CTL_EVENT_MASK_VALUE: u32 = 1;
+
+/// Reads the volume of the configured ALSA control, or of a Pulse/PipeWire sink when `sink` is set.
+pub struct Volume {
+    config: VolumeConfig,
+    has_pactl: bool,
+    /// Set while the configured control or sink fails, so the fallback is only logged once.
+    falling_back: AtomicBool,
+}
+
+impl Volume {
+    pub fn new(config: VolumeConfig) -> Self {
+        Volume { config, has_pactl: crate::command_exists("pactl"), falling_back: AtomicBool::new(false) }
+    }
+
+    /// Whether the configured backend's tool is installed.
+    pub fn available(config: &VolumeConfig) -> bool {
+        match config.sink {
+            Some(_) => crate::command_exists("pactl") || crate::command_exists("wpctl"),
+            None => crate::command_exists("amixer"),
+        }
+    }
+
+    async fn read(&self, target: Target<'_>) -> Result<Level> {
+        match target {
+            Target::Alsa { control, card } => {
+                let card = card.map(|c| c.to_string());
+                let mut args = Vec::new();
+                if let Some(card) = &card {
+                    args.extend(["-c", card.as_str()]);
+                }
+                args.extend(["sget", control]);
+                let output = crate::run_command("amixer", &args).await?;
+                Level::parse(&output, "Front Left:", "Front Right:").with_context(|| format!("No volume in amixer output for '{}'", control))
+            }
+            Target::Sink(sink) if self.has_pactl => {
+                let sink = if sink == DEFAULT_SINK { "@DEFAULT_SINK@" } else { sink };
+                let output = crate::run_command("pactl", &["get-sink-volume", sink]).await?;
+                Level::parse(&output, "front-left:", "front-right:").with_context(|| format!("No volume in pactl output for '{}'", sink))
+            }
+            Target::Sink(sink) => {
+                let sink = if sink == DEFAULT_SINK { "@DEFAULT_AUDIO_SINK@" } else { sink };
+                // `Volume: 0.40`, with ` [MUTED]` appended when muted. wpctl has no per-channel volumes.
+                let output = crate::run_command("wpctl", &["get-volume", sink]).await?;
+                let volume: f64 = output
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|v| v.parse().ok())
+                    .with_context(|| format!("No volume in wpctl output for '{}'", sink))?;
+                let percent = (volume * 100.0).round() as u32;
+                Ok(Level { left: percent, right: percent })
+            }
+        }
+    }
+
+    /// The human-readable name of `sink`: its description from pactl, or its nick from wpctl.
+    async fn sink_name(&self, sink: &str) -> Result<String> {
+        if self.has_pactl {
+            let name = match sink {
+                DEFAULT_SINK => crate::run_command("pactl", &["get-default-sink"]).await?.trim().to_string(),
+                sink => sink.to_string(),
+            };
+            let sinks = crate::run_command("pactl", &["list", "sinks"]).await?;
+            return Ok(pactl_description(&sinks, &name).unwrap_or(&name).to_string());
+        }
+        let sink = if sink == DEFAULT_SINK { "@DEFAULT_AUDIO_SINK@" } else { sink };
+        let output = crate::run_command("wpctl", &["inspect", sink]).await?;
+        ["node.nick", "node.description", "node.name"]
+            .into_iter()
+            .find_map(|key| wpctl_property(&output, key))
+            .map(str::to_string)
+            .with_context(|| format!("No name in wpctl output for '{}'", sink))
+    }
+}
+
+/// The `Description:` of the sink called `name` in `pactl list sinks` output.
+fn pactl_description<'a>(sinks: &'a str, name: &str) -> Option<&'a str> {
+    let mut lines = sinks.lines().map(str::trim).skip_while(|line| line.strip_prefix("Name: ") != Some(name));
+    lines.next()?;
+    lines.take_while(|line| !line.starts_with("Name: ")).find_map(|line| line.strip_prefix("Description: "))
+}
+
+/// A property from `wpctl inspect` output, whose lines look like `  * node.nick = "E30"`.
+fn wpctl_property<'a>(output: &'a str, key: &str) -> Option<&'a str> {
+    output.lines().find_map(|line| {
+        let (k, value) = line.trim_start_matches([' ', '*']).split_once(" = ")?;
+        (k == key).then(|| value.trim_matches('"'))
+    })
+}
+
+#[derive(Clone, Copy)]
+enum Target<'a> {
+    Alsa { control: &'a str, card: Option<u32> },
+    Sink(&'a str),
+}
+
+impl Target<'_> {
+    fn fallback(self) -> Self {
+        match self {
+            Target::Alsa { .. } => Target::Alsa { control: "Master", card: None },
+            Target::Sink(_) => Target::Sink(DEFAULT_SINK),
+        }
+    }
+
+    fn is_default(self) -> bool {
+        matches!(self, Target::Alsa { control: "Master", card: None } | Target::Sink(DEFAULT_SINK))
+    }
+}
+
+/// Left and right channel volume in percent; equal for mono controls.
+#[derive(Debug, Clone, Copy)]
+struct Level {
+    left: u32,
+    right: u32,
+}
+
+impl Level {
+    /// Reads the two channels from the lines or comma-separated parts starting at `left` and
+    /// `right`, e.g. amixer's `Front Left: Playback 49151 [75%] [on]`. Output without them (a mono
+    /// control) gives the first percentage for both.
+    fn parse(output: &str, left: &str, right: &str) -> Option<Self> {
+        match (channel_percent(output, left), channel_percent(output, right)) {
+            (Some(left), Some(right)) => Some(Level { left, right }),
+            _ => first_percent(output).map(|percent| Level { left: percent, right: percent }),
+        }
+    }
+}
+
+fn channel_percent(output: &str, label: &str) -> Option<u32> {
+    let start = output.find(label)? + label.len();
+    first_percent(output[start..].split(['\n', ',']).next()?)
+}
+
+/// The first `NN%` in the output, e.g. `[75%]` from amixer or `/  65% /` from pactl.
+fn first_percent(output: &str) -> Option<u32> {
+    let (before, _) = output.split_once('%')?;
+    before.rsplit(|c: char| !c.is_ascii_digit()).next()?.parse().ok()
+}
+
+/// Sends `id` on the trigger channel whenever PulseAudio reports a volume, mute or default sink
+/// change, so the module updates right away instead of on its next poll. This needs PulseAudio's
+/// D-Bus protocol (`module-dbus-protocol`); PipeWire doesn't offer it, in which case this errors
+/// straight away and the module keeps polling. Returns when the connection ends.
+pub async fn watch(id: ModuleId, trigger_tx: broadcast::Sender<ModuleId>) -> Result<()> {
+    // The server isn't on the session bus itself; it publishes a peer-to-peer address there.
+    let session = Connection::session().await?;
+    let lookup = PropertiesProxy::builder(&session)
+        .destination("org.PulseAudio1")?
+        .path("/org/pulseaudio/server_lookup1")?
+        .build()
+        .await?;
+    let address: String = lookup
+        .get(InterfaceName::from_static_str_unchecked("org.PulseAudio.ServerLookup1"), "Address")
+        .await?
+        .try_into()?;
+    let conn = connection::Builder::address(address.as_str())?.p2p().build().await?;
+    // The server only emits the signals it was asked for; no object paths means every device.
+    for signal in PULSE_SIGNALS {
+        conn.call_method(None::<&str>, "/org/pulseaudio/core1", Some("org.PulseAudio.Core1"), "ListenForSignal", &(*signal, Vec::<OwnedObjectPath>::new()))
+            .await?;
+    }
+    tracing::debug!(target: "monitor::volume", "Listening for PulseAudio volume changes at {}", address);
+
+    let mut stream = MessageStream::from(&conn);
+    while let Some(msg) = stream.next().await {
+        let msg = msg?;
+        let header = msg.header();
+        if header.message_type() != message::Type::Signal {
+            continue;
+        }
+        let (Some(interface), Some(member)) = (header.interface(), header.member()) else {
+            continue;
+        };
+        if PULSE_SIGNALS.contains(&format!("{}.{}", interface, member).as_str()) {
+            tracing::debug!(target: "monitor::volume", path = ?header.path(), "{} changed", member);
+            let _ = trigger_tx.send(id.clone());
+        }
+    }
+    Ok(())
+}
+
+/// Sends `id` on the trigger channel whenever a control on ALSA card `card` changes value, by
+/// subscribing to events on its control device like `alsactl monitor` does. Blocks, so it runs on
+/// its own thread; returns when the device goes away.
+#[cfg(target_os = "linux")]
+pub fn watch_alsa(card: u32, id: ModuleId, trigger_tx: broadcast::Sender<ModuleId>) -> Result<()> {
+    use std::io::Read;
+    use std::os::fd::AsRawFd;
+
+    let path = format!("/dev/snd/controlC{}", card);
+    let mut device = std::fs::File::open(&path).with_context(|| format!("Cannot open {}", path))?;
+    let mut subscribe: libc::c_int = 1;
+    let ret = unsafe { libc::ioctl(device.as_raw_fd(), CTL_SUBSCRIBE_EVENTS, &mut subscribe) };
+    if ret < 0 {
+        return Err(std::io::Error::last_os_error()).with_context(|| format!("Cannot subscribe to events on {}", path));
+    }
+    let field = |event: &[u8], offset: usize| u32::from_ne_bytes([event[offset], event[offset + 1], event[offset + 2], event[offset + 3]]);
+    let mut buf = [0u8; CTL_EVENT_SIZE * 16];
+    loop {
+        let len = device.read(&mut buf)?;
+        if len == 0 {
+            return Ok(());
+        }
+        let changed = buf[..len]
+            .chunks_exact(CTL_EVENT_SIZE)
+            .any(|event| field(event, 0) == CTL_EVENT_ELEM && field(event, 4) & CTL_EVENT_MASK_VALUE != 0);
+        if changed {
+            tracing::debug!(target: "monitor::volume", card, "ALSA mixer changed");
+            let _ = trigger_tx.send(id.clone());
+        }
+    }
+}
+
+/// Shows the volume, e.g. `vol: 75%`, or `vol: L75% R50%` when the channels differ. Each run
+/// re-resolves the default sink, so plugging in a device that becomes the default switches what
+/// is shown. A configured control or sink that can't be read falls back to the default one.
+pub async fn monitor(volume: &Volume) -> Result<String> {
+    let mut target = match &volume.config.sink {
+        Some(sink) => Target::Sink(sink),
+        None => Target::Alsa { control: &volume.config.control, card: volume.config.card },
+    };
+    let level = match volume.read(target).await {
+        Ok(level) => {
+            volume.falling_back.store(false, Ordering::Relaxed);
+            level
+        }
+        Err(e) if target.is_default() => return Err(e),
+        Err(e) => {
+            if !volume.falling_back.swap(true, Ordering::Relaxed) {
+                tracing::warn!(target: "monitor::volume", "Configured volume control failed, using the default: {:#}", e);
+            }
+            target = target.fallback();
+            volume.read(target).await?
+        }
+    };
+    tracing::debug!(target: "monitor::volume", ?level, "Read volume");
+    let mut prefix = String::new();
+    if volume.config.show_sink_name {
+        let sink = match target {
+            Target::Sink(sink) => sink,
+            Target::Alsa { .. } => DEFAULT_SINK,
+        };
+        // A missing name shouldn't hide the volume.
+        match volume.sink_name(sink).await {
+            Ok(name) => prefix = format!("{} ", name.chars().take(SINK_NAME_LEN).collect::<String>().trim_end()),
+            Err(e) => tracing::debug!(target: "monitor::volume", "No sink name: {:#}", e),
+        }
+    }
+    Ok(if level.left == level.right {
+        format!("vol: {}{}%", prefix, level.left)
+    } else {
+        format!("vol: {}L{}% R{}%", prefix, level.left, level.right)
+    })
+}
@@ -0,0 +1,49 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::PathBuf;
+
+/// Where VRAM usage is read from, picked once at startup.
+#[derive(Debug)]
+pub enum Vram {
+    Nvidia,
+    /// The DRM card's `device` directory, holding `mem_info_vram_used` and `mem_info_vram_total`.
+    Amdgpu(PathBuf),
+}
+
+impl Vram {
+    /// Prefers `nvidia-smi`, then the first amdgpu card. `None` without a discrete GPU.
+    pub fn detect() -> Option<Self> {
+        if crate::command_exists("nvidia-smi") {
+            return Some(Vram::Nvidia);
+        }
+        let mut devices: Vec<PathBuf> = fs::read_dir("/sys/class/drm")
+            .ok()?
+            .flatten()
+            .map(|card| card.path().join("device"))
+            .filter(|device| device.join("mem_info_vram_total").exists())
+            .collect();
+        devices.sort();
+        devices.into_iter().next().map(Vram::Amdgpu)
+    }
+
+    /// Used and total VRAM in bytes. With several NVIDIA GPUs this is the first one.
+    pub async fn usage(&self) -> Result<(u64, u64)> {
+        match self {
+            Vram::Nvidia => {
+                // `8192, 12288`, in MiB.
+                let output = crate::run_command("nvidia-smi", &["--query-gpu=memory.used,memory.total", "--format=csv,noheader,nounits"]).await?;
+                let (used, total) = output.lines().next().and_then(|line| line.split_once(',')).context("No memory in nvidia-smi output")?;
+                let mib = |v: &str| v.trim().parse::<u64>().map(|v| v * 1024 * 1024);
+                Ok((mib(used)?, mib(total)?))
+            }
+            Vram::Amdgpu(device) => {
+                let read = |name: &str| -> Result<u64> {
+                    let path = device.join(name);
+                    let value = fs::read_to_string(&path).with_context(|| format!("Cannot read {}", path.display()))?;
+                    Ok(value.trim().parse()?)
+                };
+                Ok((read("mem_info_vram_used")?, read("mem_info_vram_total")?))
+            }
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use crate::config::WeatherConfig;
+use anyhow::{Context, Result};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const WTTR_URL: &str = "https://wttr.in/";
+/// wttr.in one-line format: condition icon and temperature, split on `|`.
+const WTTR_FORMAT: &str = "%c|%t";
+
+/// Weather state: the shared HTTP client plus the last good reading and when it was fetched,
+/// which is shown through network outages until `cache_ttl_secs` runs out.
+pub struct Weather {
+    client: reqwest::Client,
+    config: WeatherConfig,
+    last: Mutex<Option<(String, Instant)>>,
+}
+
+impl Weather {
+    pub fn new(client: reqwest::Client, config: WeatherConfig) -> Self {
+        Weather { client, config, last: Mutex::new(None) }
+    }
+
+    async fn fetch(&self) -> Result<String> {
+        let mut url = reqwest::Url::parse(WTTR_URL)?;
+        url.path_segments_mut()
+            .map_err(|()| anyhow::anyhow!("{} can't have a path", WTTR_URL))?
+            .pop_if_empty()
+            .push(self.config.location.as_deref().unwrap_or_default());
+        let body = self.client.get(url).query(&[("format", WTTR_FORMAT)]).send().await?.error_for_status()?.text().await?;
+        let (icon, temperature) = body.trim().split_once('|').with_context(|| format!("Unexpected wttr.in response: {}", body))?;
+        Ok(format!("weather: {} {}", icon.trim(), temperature.trim().trim_start_matches('+')))
+    }
+}
+
+/// The current condition and temperature from wttr.in, e.g. `weather: ⛅ 18°C`. When fetching
+/// fails, the last good reading is shown with a `*` while it is younger than `cache_ttl_secs`.
+pub async fn monitor(weather: &Weather) -> Result<String> {
+    match weather.fetch().await {
+        Ok(text) => {
+            *weather.last.lock().unwrap() = Some((text.clone(), Instant::now()));
+            Ok(text)
+        }
+        Err(e) => {
+            let ttl = Duration::from_secs(weather.config.cache_ttl_secs);
+            match &*weather.last.lock().unwrap() {
+                Some((text, fetched)) if fetched.elapsed() <= ttl => {
+                    tracing::warn!(target: "monitor::weather", "Fetching weather failed, showing the reading from {:?} ago: {:#}", fetched.elapsed(), e);
+                    Ok(format!("{}*", text))
+                }
+                _ => Err(e),
+            }
+        }
+    }
+}
@@ -0,0 +1,64 @@
+use anyhow::{Context, Result};
+use std::borrow::Cow;
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{AtomEnum, PropMode, Window};
+use x11rb::wrapper::ConnectionExt;
+use x11rb::rust_connection::RustConnection;
+
+/// A connection to the X server used to set the root window name, which dwm shows as its status text.
+/// Opened once at startup; each update is a single property write on the socket rather than an
+/// `xsetroot` process.
+pub struct XRoot {
+    conn: RustConnection,
+    root: Window,
+}
+
+impl XRoot {
+    pub fn connect() -> Result<Self> {
+        let (conn, screen_num) = x11rb::connect(None).context("Cannot connect to the X server (is DISPLAY set?)")?;
+        let root = conn.setup().roots[screen_num].root;
+        Ok(XRoot { conn, root })
+    }
+
+    /// Equivalent to `xsetroot -name`: stores the raw UTF-8 bytes as WM_NAME. `&str` guarantees
+    /// valid UTF-8.
+    pub fn set_name(&self, name: &str) -> Result<()> {
+        let name = sanitize(name);
+        self.conn.change_property8(PropMode::REPLACE, self.root, AtomEnum::WM_NAME, AtomEnum::STRING, name.as_bytes())?;
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+/// Drops null bytes (from malformed command output), as dwm reads the property as a C string
+/// and would cut the status at the first one.
+fn sanitize(name: &str) -> Cow<'_, str> {
+    if name.contains('\0') {
+        Cow::Owned(name.replace('\0', ""))
+    } else {
+        Cow::Borrowed(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_names_are_borrowed() {
+        assert!(matches!(sanitize("cpu: 5% | Thu 15 Oct"), Cow::Borrowed("cpu: 5% | Thu 15 Oct")));
+        assert!(matches!(sanitize(""), Cow::Borrowed("")));
+    }
+
+    #[test]
+    fn null_bytes_are_dropped() {
+        let name = sanitize("cpu: 5%\0 | ram\0\0: 40%");
+        assert!(matches!(name, Cow::Owned(_)));
+        assert_eq!(name, "cpu: 5% | ram: 40%");
+    }
+
+    #[test]
+    fn multibyte_text_is_kept() {
+        assert_eq!(sanitize("🔋 85%\0 · Čt 15. říj"), "🔋 85% · Čt 15. říj");
+    }
+}